@@ -8,9 +8,9 @@ use reqwest::{Client as ReqwestClient, header::*};
 use warp::{Filter, http::Response};
 use chrono::{Utc};
 use magic_crypt::{MagicCryptTrait, new_magic_crypt};
-use tokio::{sync::{broadcast, mpsc}};
+use tokio::sync::mpsc;
 
-static MAGIC_KEY: &'static str = "1145141919810";
+static MAGIC_KEY: &str = "1145141919810";
 
 #[derive(Default, Deserialize, Serialize, Clone)]
 pub struct AccountInfo {
@@ -25,12 +25,12 @@ pub struct AccountInfo {
     pub last_refresh_time: i64,
 }
 
-fn serialize_string_encrypted<S>(token: &String, serializer: S) -> Result<S::Ok, S::Error>
+fn serialize_string_encrypted<S>(token: &str, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: ser::Serializer,
 {
     let crypt = new_magic_crypt!(MAGIC_KEY, 256);
-    serializer.serialize_str(crypt.encrypt_str_to_base64(&token).as_str())
+    serializer.serialize_str(crypt.encrypt_str_to_base64(token).as_str())
 }
 
 fn deserialize_string_encrypted<'de, D>(deserializer: D) -> Result<String, D::Error> 
@@ -131,7 +131,7 @@ impl AccountInfo {
         let redirect_uri = AccountInfo::REDIRECT_URI.replace("PORT", &port.to_string());
 
         //Fetch token
-        let auth_url = String::from(format!("{}?client_id={}\
+        let auth_url = format!("{}?client_id={}\
             &response_type=code\
             &redirect_uri={}\
             &scope=Xboxlive.signin+Xboxlive.offline_access\
@@ -139,7 +139,7 @@ impl AccountInfo {
             AccountInfo::AUTHORIZATION_URL,
             AccountInfo::CLIENT_ID,
             redirect_uri,
-            state));
+            state);
 
         println!("Browsing to: {}", auth_url);
 
@@ -301,11 +301,7 @@ async fn listen(port_holder: TcpListener) -> Result<ReceivedCode> {
                 if !accept_lang.is_empty() {
                     let langs = accept_lang.split(",");
                     for lang in langs {
-                        if lang.starts_with("zh_CN") {
-                            message = "你现在可以关闭这个标签了！";
-                            break;
-                        }
-                        else if lang.starts_with("zh") {
+                        if lang.starts_with("zh") {
                             message = "你现在可以关闭这个标签了！";
                             break;
                         }
@@ -318,7 +314,7 @@ async fn listen(port_holder: TcpListener) -> Result<ReceivedCode> {
                 if message.is_empty() {
                     message = "You can close this tab now!"
                 }
-                if let Ok(_) = tx.send(r).await {
+                if tx.send(r).await.is_ok() {
                     Ok(Response::builder()
                         .header(CONTENT_TYPE, "text/html; charset=UTF-8")
                         .header(CONNECTION, "close")
@@ -1,7 +1,9 @@
 use crate::account::AccountInfo;
+use crate::download::{MirrorRule, Mirrors, RetryPolicy};
 
 use serde::{Serialize, Deserialize};
 use anyhow::{Result};
+use tokio::time::Duration;
 use tokio::fs::File;
 use tokio::io::{AsyncWriteExt, AsyncReadExt};
 
@@ -11,9 +13,97 @@ pub struct LauncherConfig {
 
     pub download_chunk_size: u64,
     pub download_parallels_count: u32,
+
+    // a proxy or mirror of api.curseforge.com, the key is needed by the official one
+    #[serde(default)]
+    pub curseforge_api_url: Option<String>,
+    #[serde(default)]
+    pub curseforge_api_key: Option<String>,
+
+    // a BMCLAPI-compatible service every official host is routed to
+    #[serde(default)]
+    pub download_mirror: Option<String>,
+    // rewrites of their own, a company cache say. They win over `download_mirror`
+    #[serde(default)]
+    pub mirror_rules: Vec<MirrorRule>,
+    // hosts tried once a file failed on its own URL and its mirror, a
+    // BMCLAPI-compatible service and rules of the same form as `mirror_rules`
+    #[serde(default)]
+    pub fallback_mirror: Option<String>,
+    #[serde(default)]
+    pub fallback_rules: Vec<MirrorRule>,
+
+    #[serde(default)]
+    pub download_retry: RetrySettings,
+}
+
+// How failed chunks are retried, see RetryPolicy
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetrySettings {
+    pub attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: f64,
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl RetrySettings {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            attempts: self.attempts.max(1),
+            base_delay: Duration::from_millis(self.base_delay_ms),
+            max_delay: Duration::from_millis(self.max_delay_ms),
+            jitter: self.jitter,
+            retryable: self.retryable_statuses.clone(),
+        }
+    }
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        RetrySettings {
+            attempts: policy.attempts,
+            base_delay_ms: policy.base_delay.as_millis() as u64,
+            max_delay_ms: policy.max_delay.as_millis() as u64,
+            jitter: policy.jitter,
+            retryable_statuses: policy.retryable,
+        }
+    }
 }
 
 impl LauncherConfig {
+    pub fn mirrors(&self) -> Mirrors {
+        let mut mirrors = match &self.download_mirror {
+            Some(base_url) if !base_url.is_empty() => Mirrors::bmclapi(base_url),
+            _ => Mirrors::default(),
+        };
+        // a custom rule replaces the preset one for the same prefix
+        mirrors.rules.retain(|rule| !self.mirror_rules.iter().any(|custom| custom.from == rule.from));
+        mirrors.rules.extend(self.mirror_rules.iter().cloned());
+        mirrors
+    }
+
+    // Base URL and alternate pairs for Queue::add_fallback
+    pub fn fallbacks(&self) -> Vec<(String, String)> {
+        let mut rules = match &self.fallback_mirror {
+            Some(base_url) if !base_url.is_empty() => Mirrors::bmclapi(base_url).rules,
+            _ => Vec::new(),
+        };
+        rules.extend(self.fallback_rules.iter().cloned());
+
+        let mut fallbacks = Vec::new();
+        for rule in rules {
+            // the trailing slash keeps `from` to whole host and path segments
+            let from = rule.from.trim_end_matches('/');
+            let to = format!("{}/", rule.to.trim_end_matches('/'));
+            fallbacks.push((format!("https://{}/", from), to.clone()));
+            fallbacks.push((format!("http://{}/", from), to));
+        }
+        fallbacks
+    }
+
     pub async fn save(&self, path: &str) -> Result<usize>{
         let mut _file = File::create(path).await?;
 
@@ -34,3 +124,29 @@ impl LauncherConfig {
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_fallbacks_and_partial_retry_settings() {
+        let config: LauncherConfig = serde_json::from_str(r#"{
+            "accounts": [],
+            "download_chunk_size": 1048576,
+            "download_parallels_count": 8,
+            "fallback_rules": [{ "from": "maven.example.com/", "to": "https://cache.test/maven" }],
+            "download_retry": { "attempts": 2, "retryable_statuses": [503] }
+        }"#).unwrap();
+
+        assert_eq!(config.fallbacks(), [
+            ("https://maven.example.com/".to_owned(), "https://cache.test/maven/".to_owned()),
+            ("http://maven.example.com/".to_owned(), "https://cache.test/maven/".to_owned()),
+        ]);
+
+        let policy = config.download_retry.policy();
+        assert_eq!(policy.attempts, 2);
+        assert_eq!(policy.retryable, [503]);
+        assert_eq!(policy.max_delay, RetryPolicy::default().max_delay);
+    }
+}
@@ -0,0 +1,284 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub enum CrashCause {
+    WrongJavaVersion { required: Option<u32> },
+    OutOfMemory,
+    MissingMod,
+    DuplicateMod,
+    MixinFailure,
+    GraphicsDriver,
+    CorruptJar,
+    Unknown,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct CrashAnalysis {
+    pub cause: CrashCause,
+    pub suggestion: String,
+    pub evidence: String, // the line that gave it away
+    pub source: Option<PathBuf>, // None when found in the captured log
+}
+
+// Checked in order, the first match wins
+const RULES: &[(CrashCause, &[&str])] = &[
+    (CrashCause::WrongJavaVersion { required: None }, &[
+        "java.lang.UnsupportedClassVersionError",
+        "has been compiled by a more recent version of the Java Runtime",
+        "cannot be cast to class java.net.URLClassLoader",
+    ]),
+    (CrashCause::OutOfMemory, &[
+        "java.lang.OutOfMemoryError",
+        "Could not reserve enough space for",
+        "There is insufficient memory for the Java Runtime Environment",
+    ]),
+    (CrashCause::DuplicateMod, &[
+        "DuplicateModsFoundException",
+        "Found duplicate mods",
+        "Duplicate mods found",
+        "duplicate mod",
+    ]),
+    (CrashCause::MissingMod, &[
+        "MissingModsException",
+        "Missing or unsupported mandatory dependencies",
+        "Incompatible mod set",
+        "Unmet dependency",
+        "which is missing!",
+    ]),
+    (CrashCause::MixinFailure, &[
+        "MixinApplyError",
+        "MixinTransformerError",
+        "InvalidMixinException",
+        "Mixin apply failed",
+        "Mixin prepare failed",
+    ]),
+    (CrashCause::CorruptJar, &[
+        "java.util.zip.ZipException",
+        "invalid LOC header",
+        "Invalid or corrupt jarfile",
+        "zip END header not found",
+        "java.lang.SecurityException: SHA1 digest error",
+    ]),
+    (CrashCause::GraphicsDriver, &[
+        "Pixel format not accelerated",
+        "The driver does not appear to support OpenGL",
+        "GLFW error 65542",
+        "GLFW error 65543",
+        "No OpenGL context found in the current thread",
+        "org.lwjgl.LWJGLException",
+        "atio6axx.dll",
+        "ig9icd64.dll",
+        "ig75icd64.dll",
+        "nvoglv64.dll",
+        "libGL.so",
+    ]),
+];
+
+impl CrashCause {
+    pub fn suggestion(&self) -> String {
+        match self {
+            CrashCause::WrongJavaVersion { required: Some(major) } =>
+                format!("This game or one of its mods needs Java {}, select a matching Java runtime", major),
+            CrashCause::WrongJavaVersion { required: None } =>
+                "The selected Java runtime doesn't match this version, older versions need Java 8".to_owned(),
+            CrashCause::OutOfMemory =>
+                "The game ran out of memory, raise the maximum heap (-Xmx) or remove some mods".to_owned(),
+            CrashCause::MissingMod =>
+                "A mod dependency is missing or has the wrong version, install the mods named in the log".to_owned(),
+            CrashCause::DuplicateMod =>
+                "The same mod is installed twice, remove the older copy from the mods folder".to_owned(),
+            CrashCause::MixinFailure =>
+                "A mod failed to patch the game, it is likely incompatible with this game or loader version".to_owned(),
+            CrashCause::GraphicsDriver =>
+                "The graphics driver failed, update it or make sure the game runs on the dedicated GPU".to_owned(),
+            CrashCause::CorruptJar =>
+                "A jar file is damaged, verify the game files and download them again".to_owned(),
+            CrashCause::Unknown =>
+                "No known cause was found, check the crash report for details".to_owned(),
+        }
+    }
+}
+
+// Run after a non-zero exit. `since` is the launch time in seconds, so reports
+// left over from earlier runs are ignored
+pub fn analyze<P: AsRef<Path>>(game_dir: P, pid: u32, since: i64, log_tail: &[String]) -> CrashAnalysis {
+    let game_dir = game_dir.as_ref();
+    let since = UNIX_EPOCH + Duration::from_secs(since.max(0) as u64);
+
+    let mut reports = recent_files(&game_dir.join("crash-reports"), since, |name| name.ends_with(".txt"));
+    // the JVM's own fatal error log, ours first in case several games crashed
+    let own = format!("hs_err_pid{}.log", pid);
+    reports.extend(recent_files(game_dir, since, |name| name == own));
+    reports.extend(recent_files(game_dir, since, |name| {
+        name != own && name.starts_with("hs_err_pid") && name.ends_with(".log")
+    }));
+
+    for report in &reports {
+        if let Ok(text) = std::fs::read_to_string(report) {
+            if let Some(analysis) = match_text(text.lines()) {
+                return CrashAnalysis { source: Some(report.clone()), ..analysis };
+            }
+        }
+    }
+    if let Some(analysis) = match_text(log_tail.iter().map(|line| line.as_str())) {
+        return analysis;
+    }
+
+    CrashAnalysis {
+        cause: CrashCause::Unknown,
+        suggestion: CrashCause::Unknown.suggestion(),
+        evidence: String::new(),
+        source: reports.into_iter().next(),
+    }
+}
+
+// Newest first
+fn recent_files<F>(dir: &Path, since: std::time::SystemTime, filter: F) -> Vec<PathBuf>
+where F: Fn(&str) -> bool,
+{
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| filter(&entry.file_name().to_string_lossy()))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .filter(|(modified, _)| *modified >= since)
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+fn match_text<'a, I>(lines: I) -> Option<CrashAnalysis>
+where I: Iterator<Item = &'a str> + Clone,
+{
+    for (cause, patterns) in RULES {
+        let evidence = lines.clone().find(|line| patterns.iter().any(|pattern| line.contains(pattern)));
+
+        if let Some(evidence) = evidence {
+            let cause = match cause {
+                CrashCause::WrongJavaVersion { .. } => CrashCause::WrongJavaVersion {
+                    required: required_java(evidence),
+                },
+                cause => cause.clone(),
+            };
+            return Some(CrashAnalysis {
+                suggestion: cause.suggestion(),
+                cause,
+                evidence: evidence.trim().to_owned(),
+                source: None,
+            });
+        }
+    }
+    None
+}
+
+// "class file version 61.0" means Java 17; the URLClassLoader cast only fails
+// on Java 9+ for launchwrapper versions that need Java 8
+fn required_java(line: &str) -> Option<u32> {
+    if line.contains("java.net.URLClassLoader") {
+        return Some(8);
+    }
+    let rest = &line[line.find("class file version ")? + "class file version ".len()..];
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse::<u32>().ok().filter(|version| *version > 44).map(|version| version - 44)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|line| line.to_owned()).collect()
+    }
+
+    fn now() -> i64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+    }
+
+    // Written an hour before the launch `analyze` is told about
+    fn stale(path: &Path, text: &str) {
+        std::fs::write(path, text).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(3600)).unwrap();
+    }
+
+    #[test]
+    fn first_rule_wins() {
+        // out of memory is checked before the corrupt jar that comes first in the log
+        let analysis = match_text(["java.util.zip.ZipException: invalid LOC header", "java.lang.OutOfMemoryError: Java heap space"].into_iter()).unwrap();
+        assert_eq!(analysis.cause, CrashCause::OutOfMemory);
+        assert_eq!(analysis.evidence, "java.lang.OutOfMemoryError: Java heap space");
+
+        let analysis = match_text(["Mixin apply failed", "\tat MissingModsException"].into_iter()).unwrap();
+        assert_eq!(analysis.cause, CrashCause::MissingMod);
+
+        assert!(match_text(["[main] Loading"].into_iter()).is_none());
+    }
+
+    #[test]
+    fn maps_class_file_versions_to_java() {
+        let line = "java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 61.0), this version of the Java Runtime only recognizes class file versions up to 52.0";
+        assert_eq!(required_java(line), Some(17));
+        assert_eq!(required_java("class file version 52.0"), Some(8));
+        assert_eq!(required_java("java.lang.ClassCastException: class jdk.internal.loader.ClassLoaders$AppClassLoader cannot be cast to class java.net.URLClassLoader"), Some(8));
+        assert_eq!(required_java("java.lang.UnsupportedClassVersionError"), None);
+
+        let analysis = match_text([line].into_iter()).unwrap();
+        assert_eq!(analysis.cause, CrashCause::WrongJavaVersion { required: Some(17) });
+        assert!(analysis.suggestion.contains("Java 17"));
+    }
+
+    #[test]
+    fn ignores_reports_from_earlier_runs() {
+        let game_dir = scratch_dir("crash-stale");
+        std::fs::create_dir_all(game_dir.join("crash-reports")).unwrap();
+        stale(&game_dir.join("crash-reports/crash-old-client.txt"), "java.lang.OutOfMemoryError");
+        stale(&game_dir.join("hs_err_pid1.log"), "# There is insufficient memory for the Java Runtime Environment");
+        let launched = now() - 60;
+
+        let analysis = analyze(&game_dir, 2, launched, &lines("Caused by: org.lwjgl.LWJGLException: Pixel format not accelerated"));
+        assert_eq!(analysis.cause, CrashCause::GraphicsDriver);
+        assert_eq!(analysis.source, None);
+
+        let report = game_dir.join("crash-reports/crash-new-client.txt");
+        std::fs::write(&report, "---- Minecraft Crash Report ----\njava.util.zip.ZipException: zip END header not found").unwrap();
+        let analysis = analyze(&game_dir, 2, launched, &lines("Pixel format not accelerated"));
+        assert_eq!(analysis.cause, CrashCause::CorruptJar);
+        assert_eq!(analysis.source, Some(report));
+    }
+
+    #[test]
+    fn prefers_this_games_fatal_log() {
+        let game_dir = scratch_dir("crash-hs-err");
+        std::fs::write(game_dir.join("hs_err_pid7.log"), "# Problematic frame:\n# C  [nvoglv64.dll+0x1234]").unwrap();
+        std::fs::write(game_dir.join("hs_err_pid8.log"), "# Native memory allocation (mmap) failed\n# There is insufficient memory for the Java Runtime Environment").unwrap();
+
+        let analysis = analyze(&game_dir, 8, now() - 60, &[]);
+        assert_eq!(analysis.cause, CrashCause::OutOfMemory);
+        assert_eq!(analysis.source, Some(game_dir.join("hs_err_pid8.log")));
+    }
+
+    #[test]
+    fn falls_back_to_the_log_tail() {
+        let game_dir = scratch_dir("crash-tail");
+        std::fs::create_dir_all(game_dir.join("crash-reports")).unwrap();
+        let report = game_dir.join("crash-reports/crash-client.txt");
+        std::fs::write(&report, "---- Minecraft Crash Report ----\nDescription: Unexpected error").unwrap();
+
+        let analysis = analyze(&game_dir, 1, now() - 60, &lines("[main/ERROR]: Incompatible mod set!\nsodium requires fabric-api"));
+        assert_eq!(analysis.cause, CrashCause::MissingMod);
+        assert_eq!(analysis.evidence, "[main/ERROR]: Incompatible mod set!");
+        assert_eq!(analysis.source, None);
+
+        let analysis = analyze(&game_dir, 1, now() - 60, &lines("[main/INFO]: Stopping!"));
+        assert_eq!(analysis.cause, CrashCause::Unknown);
+        assert_eq!(analysis.source, Some(report));
+    }
+}
@@ -1,13 +1,20 @@
 use serde_json::{Value as Json};
 use serde::{Deserialize, Deserializer, de::{MapAccess, Visitor}};
 
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
+use std::path::{Path, PathBuf};
 
 use crate::download::Task;
+use crate::launch::CLASSPATH_SEPARATOR;
+use crate::util::compare_versions;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct LaunchArguments {
+    #[serde(default)]
     pub game: Vec<Argument>,
+    #[serde(default)]
     pub jvm: Vec<Argument>,
 }
 
@@ -111,7 +118,7 @@ impl Rule {
             }
         }
         if let Some(features) = &self.features {
-            if let Some(_) = features.is_demo_user {
+            if !features.matches() {
                 return !default;
             }
         }
@@ -130,6 +137,29 @@ pub struct OsRule {
 pub struct FeatureRule {
     pub is_demo_user: Option<bool>,
     pub has_custom_resolution: Option<bool>,
+    pub has_quick_plays_support: Option<bool>, // 1.20 and later
+    pub is_quick_play_singleplayer: Option<bool>,
+    pub is_quick_play_multiplayer: Option<bool>,
+    pub is_quick_play_realms: Option<bool>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Json>, // features newer versions add
+}
+
+impl FeatureRule {
+    // None of the features are enabled, so a rule only matches where every
+    // feature it names is asked to be off
+    pub fn matches(&self) -> bool {
+        let known = [
+            self.is_demo_user,
+            self.has_custom_resolution,
+            self.has_quick_plays_support,
+            self.is_quick_play_singleplayer,
+            self.is_quick_play_multiplayer,
+            self.is_quick_play_realms,
+        ];
+        known.iter().flatten().all(|wanted| !wanted) &&
+            self.other.values().all(|wanted| wanted == &Json::Bool(false))
+    }
 }
 
 
@@ -146,7 +176,7 @@ pub struct AssetConfig {
 #[derive(Deserialize)]
 pub struct MainDownloadItems{
     pub client: DownloadItem,
-    pub client_mappings: DownloadItem,
+    pub client_mappings: Option<DownloadItem>, // 1.14 and later
 }
 
 #[derive(Deserialize)]
@@ -156,6 +186,16 @@ pub struct JavaVersion {
     pub major_version: u32,
 }
 
+// Versions that predate `javaVersion` all run on Java 8
+impl Default for JavaVersion {
+    fn default() -> Self {
+        JavaVersion {
+            component: "jre-legacy".to_owned(),
+            major_version: 8,
+        }
+    }
+}
+
 #[derive(Deserialize, Default)]
 pub struct DownloadItem {
     #[serde(alias="id")]
@@ -166,9 +206,9 @@ pub struct DownloadItem {
     pub url: String,
 }
 
-impl Into<Task> for DownloadItem {
-    fn into(self) -> Task {
-        Task::new(&self.url, &self.path, self.size)
+impl From<DownloadItem> for Task {
+    fn from(item: DownloadItem) -> Task {
+        Task::new(&item.url, &item.path, item.size).with_sha1(&item.sha1)
     }
 }
 
@@ -201,13 +241,19 @@ impl<'de> Deserialize<'de> for Library {
                 let mut name: String = String::new();
                 let mut is_native: bool = false;
                 let mut extract_exclude: Vec<String> = Vec::new();
+                let mut repository: String = String::new();
+                let mut sha1: String = String::new();
+                let mut size: u64 = 0;
                         
                 while let Some(key) = map.next_key::<String>()? {
                     match key.as_str() {
                         "name" => name = map.next_value::<String>()?,
+                        "url" => repository = map.next_value::<String>()?,
+                        "sha1" => sha1 = map.next_value::<String>()?,
+                        "size" => size = map.next_value::<u64>()?,
                         "downloads" => {
                             download = map.next_value::<LibraryDownload>()?;
-                            is_native = download.classifiers != None;
+                            is_native = download.classifiers.is_some();
                         },
                         "natives" => {
                             let natives = map.next_value::<Json>()?;
@@ -223,6 +269,8 @@ impl<'de> Deserialize<'de> for Library {
                             else {
                                 native = "".to_owned()
                             }
+                            // e.g. natives-windows-${arch} in older versions
+                            native = native.replace("${arch}", if cfg!(target_pointer_width="64") { "64" } else { "32" });
                         },
                         "rules" => {
                             rules = map.next_value::<Vec<Rule>>()?;
@@ -254,11 +302,23 @@ impl<'de> Deserialize<'de> for Library {
                     download.artifact
                 };
 
+                // Loader profiles only give a maven name and repository, Fabric
+                // and Quilt meta also a sha1 and size next to them
+                let mut download_item = download_item;
+                if download_item.url.is_empty() && !repository.is_empty() {
+                    if let Some(coordinate) = MavenCoordinate::parse(&name) {
+                        download_item.path = coordinate.url_path();
+                        download_item.url = coordinate.url(&repository);
+                        download_item.sha1 = sha1;
+                        download_item.size = size;
+                    }
+                }
+
                 Ok(Library {
-                    download_item: download_item,
-                    name: name,
-                    is_native: is_native,
-                    extract_exclude: extract_exclude,
+                    download_item,
+                    name,
+                    is_native,
+                    extract_exclude,
                 })
             }
         }
@@ -266,9 +326,158 @@ impl<'de> Deserialize<'de> for Library {
     }
 }
 
+impl Library {
+    // Relative to the libraries root; falls back to the maven layout when the
+    // profile doesn't carry a downloads block
+    pub fn path(&self) -> PathBuf {
+        if !self.download_item.path.is_empty() {
+            PathBuf::from(&self.download_item.path)
+        }
+        else {
+            MavenCoordinate::parse(&self.name)
+                .map(|coordinate| coordinate.path())
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct MavenCoordinate {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    pub classifier: Option<String>,
+    pub extension: String,
+}
+
+impl MavenCoordinate {
+    // group:artifact:version[:classifier][@extension]
+    pub fn parse(name: &str) -> Option<MavenCoordinate> {
+        let (name, extension) = match name.split_once('@') {
+            Some((name, extension)) => (name, extension),
+            None => (name, "jar"),
+        };
+        let mut parts = name.split(':');
+
+        let group = parts.next().filter(|s| !s.is_empty())?;
+        let artifact = parts.next().filter(|s| !s.is_empty())?;
+        let version = parts.next().filter(|s| !s.is_empty())?;
+        let classifier = parts.next().map(|s| s.to_owned());
+
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(MavenCoordinate {
+            group: group.to_owned(),
+            artifact: artifact.to_owned(),
+            version: version.to_owned(),
+            classifier,
+            extension: extension.to_owned(),
+        })
+    }
+
+    // Libraries sharing this key are the same library in different versions
+    pub fn key(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}:{}:{}", self.group, self.artifact, classifier),
+            None => format!("{}:{}", self.group, self.artifact),
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", self.artifact, self.version, classifier, self.extension),
+            None => format!("{}-{}.{}", self.artifact, self.version, self.extension),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        let mut path = PathBuf::new();
+        for part in self.group.split('.') {
+            path.push(part);
+        }
+        path.push(&self.artifact);
+        path.push(&self.version);
+        path.push(self.file_name());
+        path
+    }
+
+    // Same as `path`, always '/' separated
+    pub fn url_path(&self) -> String {
+        format!("{}/{}/{}/{}", self.group.replace('.', "/"), self.artifact, self.version, self.file_name())
+    }
+
+    pub fn url(&self, repository: &str) -> String {
+        format!("{}/{}", repository.trim_end_matches('/'), self.url_path())
+    }
+}
+
+pub struct Classpath {
+    pub entries: Vec<PathBuf>,
+    pub missing: Vec<PathBuf>,
+}
+
+impl Classpath {
+    pub fn resolve<P, Q>(libraries: &[Library], library_root: P, client_jar: Q) -> Classpath
+    where
+            P: AsRef<Path>,
+            Q: AsRef<Path>, {
+        let library_root = library_root.as_ref();
+
+        // key -> (position, version), so a newer duplicate replaces the older one in place
+        let mut seen: HashMap<String, (usize, String)> = HashMap::new();
+        let mut entries: Vec<PathBuf> = Vec::new();
+
+        for library in libraries {
+            if library.is_native {
+                continue;
+            }
+            let path = library_root.join(library.path());
+
+            match MavenCoordinate::parse(&library.name) {
+                Some(coordinate) => {
+                    match seen.get_mut(&coordinate.key()) {
+                        Some((index, version)) => {
+                            if compare_versions(&coordinate.version, version) == Ordering::Greater {
+                                entries[*index] = path;
+                                *version = coordinate.version;
+                            }
+                        },
+                        None => {
+                            seen.insert(coordinate.key(), (entries.len(), coordinate.version));
+                            entries.push(path);
+                        },
+                    }
+                },
+                None => {
+                    if !entries.contains(&path) {
+                        entries.push(path);
+                    }
+                },
+            }
+        }
+        entries.push(client_jar.as_ref().to_path_buf());
+
+        let missing = entries.iter()
+            .filter(|path| !path.is_file())
+            .cloned()
+            .collect();
+
+        Classpath { entries, missing }
+    }
+
+    pub fn join(&self) -> String {
+        self.entries.iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(CLASSPATH_SEPARATOR)
+    }
+}
+
 #[derive(Deserialize, Default)]
 pub struct LibraryDownload {
-    pub artifact: DownloadItem,
+    #[serde(default)]
+    pub artifact: DownloadItem, // absent on natives-only libraries like lwjgl-platform
     pub classifiers: Option<Json>
 }
 
@@ -348,3 +557,164 @@ impl<'de> Deserialize<'de> for WrapperVec {
         deserializer.deserialize_map(OuterVisitor)
     }
 }
+
+// java-runtime all.json: platform -> component -> builds
+pub type RuntimeIndex = HashMap<String, HashMap<String, Vec<RuntimeBuild>>>;
+
+#[derive(Deserialize)]
+pub struct RuntimeBuild {
+    pub manifest: DownloadItem,
+    pub version: RuntimeVersion,
+}
+
+#[derive(Deserialize)]
+pub struct RuntimeVersion {
+    pub name: String,
+    pub released: String,
+}
+
+#[derive(Deserialize)]
+pub struct RuntimeManifest {
+    pub files: HashMap<String, RuntimeFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag="type", rename_all="lowercase")]
+pub enum RuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Directory,
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct RuntimeFileDownloads {
+    pub raw: DownloadItem,
+    pub lzma: Option<DownloadItem>,
+}
+
+// install_profile.json inside a modern (1.13+) Forge or NeoForge installer
+#[derive(Deserialize)]
+pub struct InstallProfile {
+    pub spec: Option<u32>,
+    pub profile: String,
+    pub version: String,
+    pub minecraft: String,
+    pub json: String, // the version JSON, as a path inside the installer
+    pub path: Option<String>,
+    #[serde(default)]
+    pub data: HashMap<String, SidedData>,
+    #[serde(default)]
+    pub processors: Vec<Processor>,
+    #[serde(default, deserialize_with="crate::instance::deserialize_skip_error")]
+    pub libraries: Vec<Library>,
+}
+
+#[derive(Deserialize)]
+pub struct SidedData {
+    pub client: String,
+    pub server: String,
+}
+
+#[derive(Deserialize)]
+pub struct Processor {
+    pub jar: String,
+    #[serde(default)]
+    pub classpath: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+    pub sides: Option<Vec<String>>,
+}
+
+// install_profile.json of the 1.7.10 - 1.12.2 installers
+#[derive(Deserialize)]
+pub struct LegacyInstallProfile {
+    pub install: LegacyInstall,
+    #[serde(rename = "versionInfo")]
+    pub version_info: Json,
+}
+
+#[derive(Deserialize)]
+pub struct LegacyInstall {
+    pub target: String, // id of the version it installs
+    pub path: String, // maven name of the universal jar
+    #[serde(rename = "filePath")]
+    pub file_path: String, // the universal jar inside the installer
+    pub minecraft: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn library(name: &str) -> Library {
+        serde_json::from_value(json!({ "name": name, "url": "https://maven.test/" })).unwrap()
+    }
+
+    #[test]
+    fn parses_maven_coordinates() {
+        let plain = MavenCoordinate::parse("org.ow2.asm:asm:9.6").unwrap();
+        assert_eq!(plain.key(), "org.ow2.asm:asm");
+        assert_eq!(plain.url_path(), "org/ow2/asm/asm/9.6/asm-9.6.jar");
+        assert_eq!(plain.path(), Path::new("org").join("ow2").join("asm").join("asm").join("9.6").join("asm-9.6.jar"));
+        assert_eq!(plain.url("https://maven.test/"), "https://maven.test/org/ow2/asm/asm/9.6/asm-9.6.jar");
+
+        let classified = MavenCoordinate::parse("de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412:mappings@txt").unwrap();
+        assert_eq!(classified.key(), "de.oceanlabs.mcp:mcp_config:mappings");
+        assert_eq!(classified.file_name(), "mcp_config-1.20.1-20230612.114412-mappings.txt");
+
+        assert!(MavenCoordinate::parse("org.ow2.asm:asm").is_none());
+        assert!(MavenCoordinate::parse("a:b:c:d:e").is_none());
+        assert!(MavenCoordinate::parse("::1.0").is_none());
+    }
+
+    #[test]
+    fn classpath_keeps_the_newest_duplicate_in_place() {
+        let libraries = [
+            library("org.ow2.asm:asm:9.3"),
+            library("com.google.guava:guava:31.1-jre"),
+            library("org.ow2.asm:asm:9.6"),
+            library("org.ow2.asm:asm:9.5"),
+            library("org.lwjgl:lwjgl:3.3.1:natives-linux"),
+        ];
+        let classpath = Classpath::resolve(&libraries, "/libraries", "/versions/1.20.1/1.20.1.jar");
+
+        let root = Path::new("/libraries");
+        assert_eq!(classpath.entries, [
+            root.join("org/ow2/asm/asm/9.6/asm-9.6.jar"),
+            root.join("com/google/guava/guava/31.1-jre/guava-31.1-jre.jar"),
+            root.join("org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar"),
+            PathBuf::from("/versions/1.20.1/1.20.1.jar"),
+        ]);
+        assert_eq!(classpath.missing, classpath.entries);
+    }
+
+    #[test]
+    fn classpath_leaves_out_natives() {
+        let native: Library = serde_json::from_value(json!({
+            "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+            "natives": { "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" },
+            "downloads": {
+                "classifiers": {
+                    "natives-linux": { "path": "lwjgl-platform-natives-linux.jar", "sha1": "", "size": 0, "url": "https://libraries.test/linux.jar" },
+                    "natives-windows": { "path": "lwjgl-platform-natives-windows.jar", "sha1": "", "size": 0, "url": "https://libraries.test/windows.jar" },
+                    "natives-osx": { "path": "lwjgl-platform-natives-osx.jar", "sha1": "", "size": 0, "url": "https://libraries.test/osx.jar" },
+                },
+            },
+        })).unwrap();
+        assert!(native.is_native);
+
+        let classpath = Classpath::resolve(&[native, library("org.ow2.asm:asm:9.6")], "/libraries", "/client.jar");
+        assert_eq!(classpath.entries.len(), 2);
+        assert_eq!(classpath.entries[1], Path::new("/client.jar"));
+    }
+}
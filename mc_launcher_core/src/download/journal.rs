@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::hash_range;
+
+// The chunks of a download already written, kept next to the file as
+// <name>.journal with one `start length sha1` line per chunk, so a later queue
+// only fetches the rest
+pub(super) struct Journal {
+    file: PathBuf,
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn for_file(file: &Path) -> Journal {
+        let mut name = OsString::from(file.as_os_str());
+        name.push(".journal");
+        Journal {
+            file: file.to_path_buf(),
+            path: PathBuf::from(name),
+        }
+    }
+
+    // The journaled chunks whose data is still on disk unchanged, as start and
+    // length. Anything else, a deleted or truncated file included, is dropped
+    pub fn load(&self) -> BTreeMap<u64, u64> {
+        let mut chunks = BTreeMap::new();
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(_) => return chunks,
+        };
+        if !self.file.is_file() {
+            self.remove();
+            return chunks;
+        }
+
+        for line in text.lines() {
+            let mut fields = line.split(' ');
+            let (start, len, sha1) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(start), Some(len), Some(sha1)) => (start, len, sha1),
+                _ => continue, // cut short by a crash
+            };
+            let (start, len) = match (start.parse::<u64>(), len.parse::<u64>()) {
+                (Ok(start), Ok(len)) => (start, len),
+                _ => continue,
+            };
+            if chunk_sha1(&self.file, start, len).is_ok_and(|actual| actual == sha1) {
+                chunks.insert(start, len);
+            }
+        }
+        chunks
+    }
+
+    pub fn record(&self, start: u64, len: u64) -> Result<()> {
+        let sha1 = chunk_sha1(&self.file, start, len)?;
+        let mut journal = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(journal, "{} {} {}", start, len, sha1)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn chunk_sha1(file: &Path, start: u64, len: u64) -> Result<String> {
+    let mut hasher = sha1::Sha1::new();
+    hash_range(&mut hasher, file, start, len)?;
+    Ok(hasher.digest().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_file(name: &str, data: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mc_launcher_core-journal-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn keeps_recorded_chunks() {
+        let file = scratch_file("recorded", &[7u8; 12]);
+        let journal = Journal::for_file(&file);
+        journal.record(0, 4).unwrap();
+        journal.record(8, 4).unwrap();
+
+        let chunks: Vec<(u64, u64)> = journal.load().into_iter().collect();
+        assert_eq!(chunks, [(0, 4), (8, 4)]);
+    }
+
+    #[test]
+    fn drops_changed_and_cut_lines() {
+        let file = scratch_file("changed", &[7u8; 12]);
+        let journal = Journal::for_file(&file);
+        journal.record(0, 4).unwrap();
+        journal.record(4, 4).unwrap();
+        std::fs::OpenOptions::new().append(true).open(&journal.path).unwrap().write_all(b"8 4").unwrap();
+
+        let mut data = std::fs::read(&file).unwrap();
+        data[5] = 0;
+        std::fs::write(&file, data).unwrap();
+
+        let chunks: Vec<(u64, u64)> = journal.load().into_iter().collect();
+        assert_eq!(chunks, [(0, 4)]);
+    }
+
+    #[test]
+    fn forgets_a_deleted_file() {
+        let file = scratch_file("deleted", &[7u8; 8]);
+        let journal = Journal::for_file(&file);
+        journal.record(0, 4).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(journal.load().is_empty());
+        assert!(!journal.path.exists());
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+// Official hosts and where a BMCLAPI-compatible service serves each of them
+const BMCLAPI_ROUTES: &[(&str, &str)] = &[
+    ("launchermeta.mojang.com", ""),
+    ("launcher.mojang.com", ""),
+    ("piston-meta.mojang.com", ""),
+    ("piston-data.mojang.com", ""),
+    ("libraries.minecraft.net", "/maven"),
+    ("resources.download.minecraft.net", "/assets"),
+    ("maven.minecraftforge.net", "/maven"),
+    ("files.minecraftforge.net/maven", "/maven"),
+    ("maven.neoforged.net/releases", "/maven"),
+    ("maven.fabricmc.net", "/maven"),
+    ("meta.fabricmc.net", "/fabric-meta"),
+];
+
+// URLs starting with `from`, scheme left out, are fetched from `to` instead
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MirrorRule {
+    pub from: String, // launchermeta.mojang.com
+    pub to: String, // https://mirror.example.com/mojang
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Mirrors {
+    pub rules: Vec<MirrorRule>,
+}
+
+impl Mirrors {
+    // Every official host routed to a BMCLAPI-compatible service at `base_url`
+    pub fn bmclapi(base_url: &str) -> Mirrors {
+        let base_url = base_url.trim_end_matches('/');
+        Mirrors {
+            rules: BMCLAPI_ROUTES.iter()
+                .map(|(from, to)| MirrorRule { from: from.to_string(), to: format!("{}{}", base_url, to) })
+                .collect(),
+        }
+    }
+
+    // `url` on the mirror of the longest matching rule, unchanged if none matches
+    pub fn rewrite(&self, url: &str) -> String {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        self.rules.iter()
+            .filter(|rule| {
+                let from = rule.from.trim_end_matches('/');
+                // whole host and path segments only
+                rest.starts_with(from) && matches!(rest[from.len()..].chars().next(), None | Some('/') | Some('?'))
+            })
+            .max_by_key(|rule| rule.from.trim_end_matches('/').len())
+            .map(|rule| format!("{}{}", rule.to.trim_end_matches('/'), &rest[rule.from.trim_end_matches('/').len()..]))
+            .unwrap_or_else(|| url.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_official_hosts_to_bmclapi() {
+        let mirrors = Mirrors::bmclapi("https://bmclapi2.bangbang93.com/");
+        assert_eq!(
+            mirrors.rewrite("https://libraries.minecraft.net/com/mojang/brigadier/1.0.18/brigadier-1.0.18.jar"),
+            "https://bmclapi2.bangbang93.com/maven/com/mojang/brigadier/1.0.18/brigadier-1.0.18.jar",
+        );
+        assert_eq!(
+            mirrors.rewrite("http://resources.download.minecraft.net/ab/abcdef"),
+            "https://bmclapi2.bangbang93.com/assets/ab/abcdef",
+        );
+        assert_eq!(
+            mirrors.rewrite("https://launchermeta.mojang.com/mc/game/version_manifest.json?t=1"),
+            "https://bmclapi2.bangbang93.com/mc/game/version_manifest.json?t=1",
+        );
+    }
+
+    #[test]
+    fn longest_rule_wins() {
+        let mirrors = Mirrors {
+            rules: vec![
+                MirrorRule { from: "example.com".to_owned(), to: "https://a.test".to_owned() },
+                MirrorRule { from: "example.com/maven/".to_owned(), to: "https://b.test/m/".to_owned() },
+            ],
+        };
+        assert_eq!(mirrors.rewrite("https://example.com/maven/x.jar"), "https://b.test/m/x.jar");
+        assert_eq!(mirrors.rewrite("https://example.com/other"), "https://a.test/other");
+    }
+
+    #[test]
+    fn only_whole_segments_match() {
+        let mirrors = Mirrors::bmclapi("https://mirror.test");
+        for url in [
+            "https://libraries.minecraft.net.evil.test/x.jar",
+            "https://maven.fabricmc.netfoo/x.jar",
+            "https://files.minecraftforge.net/mavenx/x.jar",
+            "https://example.com/libraries.minecraft.net/x.jar",
+        ] {
+            assert_eq!(mirrors.rewrite(url), url);
+        }
+    }
+}
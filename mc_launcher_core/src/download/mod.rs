@@ -1,7 +1,8 @@
+use std::collections::{BTreeMap, HashMap};
 use std::io::{SeekFrom, prelude::*};
 use std::path::{PathBuf, Path};
 use std::sync::Arc;
-use std::thread::{self, JoinHandle};
+use std::thread;
 
 use parking_lot::Mutex;
 #[cfg(target_os="linux")]
@@ -14,12 +15,21 @@ use reqwest::{Client, header::RANGE};
 use anyhow::{Result, anyhow, Error};
 use bytes::Bytes;
 
+mod journal;
+mod mirror;
+mod retry;
+use journal::Journal;
+pub use mirror::{MirrorRule, Mirrors};
+pub use retry::{RetryPolicy, StatusError};
+
 #[derive(Clone)]
 pub struct Task {
     pub url: String,
     pub path: PathBuf,
     pub size: u64,
     pub start: u64,
+    pub sha1: Option<String>, // checked once every chunk has landed
+    pub mirrors: Vec<String>, // tried in order once `url` keeps failing
 }
 
 impl Task {
@@ -31,8 +41,28 @@ impl Task {
                 path: path.as_ref().into(),
                 size,
                 start: 0,
+                sha1: None,
+                mirrors: Vec::new(),
         }
     }
+    // An empty hash, as maven-only libraries have, leaves the task unchecked
+    pub fn with_sha1(mut self, sha1: &str) -> Task {
+        self.sha1 = if sha1.is_empty() { None } else { Some(sha1.to_owned()) };
+        self
+    }
+    // The task itself for a whole-file download, otherwise one task per `chunk_size` range
+    fn split(self, chunk_size: u64) -> Vec<Task> {
+        let mut chunks = vec![self.clone()];
+        if self.size == 0 || self.start + chunk_size > self.size {
+            return chunks;
+        }
+        let mut task = self;
+        while task.start + chunk_size < task.size {
+            task.start += chunk_size;
+            chunks.push(task.clone());
+        }
+        chunks
+    }
     pub async fn get_part(&self, client: &Client, chunk_size: u64) -> Result<Bytes> {
         let end = core::cmp::min(self.start + chunk_size - 1 , self.size);
 
@@ -60,7 +90,7 @@ impl Task {
                 let basename = resp
                     .url()
                     .path_segments()
-                    .and_then(|segments| segments.last())
+                    .and_then(|mut segments| segments.next_back())
                     .and_then(|name| if name.is_empty() { None } else { Some(name) })
                     .ok_or(anyhow!("Can't parse url"))?;
                 
@@ -76,7 +106,6 @@ impl Task {
         let (res, _) = file.write_at(bytes, 0).await;
 
         let res = res?;
-        println!("RESPONSE: {} bytes from {}", res, self.url);
 
         file.sync_all().await?;
         file.close().await?;
@@ -91,7 +120,7 @@ impl Task {
                 let basename = resp
                     .url()
                     .path_segments()
-                    .and_then(|segments| segments.last())
+                    .and_then(|mut segments| segments.next_back())
                     .and_then(|name| if name.is_empty() { None } else { Some(name) })
                     .ok_or(anyhow!("Can't parse url"))?;
                 
@@ -105,10 +134,7 @@ impl Task {
         let bytes = resp.bytes().await?;
         let mut file = std::fs::File::create(&self.path)?;
         file.write_all(&bytes)?;
-        let res = bytes.len();
-
-        println!("RESPONSE: {} bytes from {}", res, self.url);
-        Ok(res)
+        Ok(bytes.len())
     }
 
     pub async fn download_part_std(&mut self, client: Client, chunk_size: u64) -> Result<u64> {
@@ -126,7 +152,7 @@ impl Task {
                 let basename = resp
                     .url()
                     .path_segments()
-                    .and_then(|segments| segments.last())
+                    .and_then(|mut segments| segments.next_back())
                     .and_then(|name| if name.is_empty() { None } else { Some(name) })
                     .ok_or(anyhow!("Can't parse url"))?;
                 
@@ -139,7 +165,7 @@ impl Task {
 
         let bytes = resp.bytes().await?;
 
-        let mut file = std::fs::OpenOptions::new().write(true).create(true).open(&self.path)?;
+        let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(false).open(&self.path)?;
         file.seek(SeekFrom::Start(self.start))?;
         file.write_all(&bytes)?;
         let res = bytes.len();
@@ -162,7 +188,7 @@ impl Task {
                 let basename = resp
                     .url()
                     .path_segments()
-                    .and_then(|segments| segments.last())
+                    .and_then(|mut segments| segments.next_back())
                     .and_then(|name| if name.is_empty() { None } else { Some(name) })
                     .ok_or(anyhow!("Can't parse url"))?;
                 
@@ -173,6 +199,9 @@ impl Task {
             std::fs::create_dir_all(self.path.parent().ok_or(anyhow!("No parent dir"))?)?;
         }
 
+        if !resp.status().is_success() {
+            return Err(StatusError { url: self.url.clone(), status: resp.status().as_u16() }.into());
+        }
         let bytes = resp.bytes().await?;
 
         let file = OpenOptions::new().write(true).create(true).open(&self.path).await?;
@@ -183,14 +212,185 @@ impl Task {
 
         res.map(|size| size as u64).map_err(anyhow::Error::new)
     }
+
+    // `download_part`, retried with backoff as `policy` allows. A URL that keeps
+    // failing, or answers a status not worth retrying, hands over to the next mirror
+    pub async fn download_part_retrying(&mut self, client: Client, chunk_size: u64, policy: &RetryPolicy) -> Result<u64> {
+        let mut urls = vec![self.url.clone()];
+        urls.append(&mut self.mirrors);
+
+        let mut last_error = anyhow!("No URL to download {} from", self.path.display());
+        for url in urls {
+            self.url = url;
+            for attempt in 1..=policy.attempts.max(1) {
+                match self.download_part(client.clone(), chunk_size).await {
+                    Ok(size) => return Ok(size),
+                    Err(e) => {
+                        let retryable = policy.is_retryable(&e);
+                        last_error = e;
+                        if !retryable || attempt == policy.attempts {
+                            break;
+                        }
+                        sleep(policy.delay(attempt)).await;
+                    },
+                }
+            }
+        }
+        Err(last_error)
+    }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ControlSignal {
     Pause,
     Continue,
     Abort,
 }
 
+// Tracks a file's chunks as they land, hashing them in order when the task has
+// a sha1. Chunks that arrive ahead of the hashed prefix wait in `landed` until
+// the gap before them is filled
+struct Verifier {
+    task: Task,
+    hasher: sha1::Sha1,
+    hashed: u64,
+    landed: BTreeMap<u64, u64>, // start, bytes written
+    chunks: usize,
+    arrived: usize,
+    attempts: u32,
+    journal: Option<Journal>, // only files split into several chunks are resumable
+}
+
+impl Verifier {
+    fn new(task: Task, chunks: usize) -> Verifier {
+        let journal = if chunks > 1 { Some(Journal::for_file(&task.path)) } else { None };
+        Verifier {
+            task,
+            hasher: sha1::Sha1::new(),
+            hashed: 0,
+            landed: BTreeMap::new(),
+            chunks,
+            arrived: 0,
+            attempts: 1,
+            journal,
+        }
+    }
+
+    // Land the chunks an earlier queue journaled. Returns their starts, and the
+    // verdict when that was every chunk
+    fn resume(&mut self, chunk_size: u64, chunks: &[Task]) -> (Vec<u64>, Option<bool>) {
+        let journaled = match &self.journal {
+            Some(journal) => journal.load(),
+            None => return (Vec::new(), None),
+        };
+        let mut resumed = Vec::new();
+        let mut verdict = None;
+        for chunk in chunks {
+            let len = chunk_size.min(self.task.size - chunk.start);
+            if journaled.get(&chunk.start) == Some(&len) {
+                resumed.push(chunk.start);
+                verdict = self.land(chunk.start, len);
+            }
+        }
+        (resumed, verdict)
+    }
+
+    fn record(&self, start: u64, written: u64) {
+        if let Some(journal) = &self.journal {
+            let _ = journal.record(start, written);
+        }
+    }
+
+    // Whether the file is whole and matches its sha1, once its last chunk has landed
+    fn land(&mut self, start: u64, written: u64) -> Option<bool> {
+        self.landed.insert(start, written);
+        self.arrived += 1;
+        while let Some(written) = self.landed.remove(&self.hashed) {
+            if written == 0 {
+                break;
+            }
+            if self.task.sha1.is_some() && hash_range(&mut self.hasher, &self.task.path, self.hashed, written).is_err() {
+                break;
+            }
+            self.hashed += written;
+        }
+        if self.arrived < self.chunks {
+            return None;
+        }
+        let complete = self.landed.is_empty() && (self.task.size == 0 || self.hashed == self.task.size);
+        let matches = self.task.sha1.as_ref().is_none_or(|sha1| self.hasher.digest().to_string().eq_ignore_ascii_case(sha1));
+        if complete && matches {
+            self.discard_journal();
+        }
+        Some(complete && matches)
+    }
+
+    // Start over for another fetch of the whole file
+    fn retry(&mut self, chunk_size: u64) -> Vec<Task> {
+        self.hasher = sha1::Sha1::new();
+        self.hashed = 0;
+        self.landed.clear();
+        self.arrived = 0;
+        self.attempts += 1;
+        // a stale tail past the new data would fail the hash again
+        let _ = std::fs::remove_file(&self.task.path);
+        self.discard_journal();
+        self.task.clone().split(chunk_size)
+    }
+
+    fn discard_journal(&self) {
+        if let Some(journal) = &self.journal {
+            journal.remove();
+        }
+    }
+}
+
+// One task per path, so no two verifiers share a file. A duplicate only fills in
+// what the first task lacks: a sha1, and its URLs as mirrors
+fn merge_by_path(tasks: Vec<Task>) -> Vec<Task> {
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    let mut merged: Vec<Task> = Vec::new();
+    for task in tasks {
+        let first = match index.get(&task.path) {
+            Some(&i) => &mut merged[i],
+            None => {
+                index.insert(task.path.clone(), merged.len());
+                merged.push(task);
+                continue;
+            },
+        };
+        if first.sha1.is_none() {
+            first.sha1 = task.sha1;
+        }
+        for url in std::iter::once(task.url).chain(task.mirrors) {
+            if url != first.url && !first.mirrors.contains(&url) {
+                first.mirrors.push(url);
+            }
+        }
+    }
+    merged
+}
+
+fn hash_range(hasher: &mut sha1::Sha1, path: &Path, start: u64, len: u64) -> Result<()> {
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut file = file.take(len);
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut read = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        read += n as u64;
+    }
+    if read < len {
+        return Err(anyhow!("{} is shorter than expected", path.display()));
+    }
+    Ok(())
+}
+
 pub struct Message {
     pub path: PathBuf,
     pub success: bool,
@@ -206,19 +406,26 @@ impl Message {
 pub struct Queue {
     pub chunk_size: u64,
     pub parallels: usize,
+    pub verify_attempts: u32, // fetches of a damaged file before it counts as corrupt
+    pub retry: RetryPolicy,
+    pub mirrors: Mirrors, // every task is fetched through these, the official URL left as a fallback
     
     progress_sender: Option<mpsc::Sender<Message>>,
     poll_duration: Duration,
     tasks: Vec<Task>,
+    fallbacks: Vec<(String, String)>,
     client: Client,
     speed: f64,
+    total: usize,
     completed: usize,
     failed: usize,
+    corrupt: Vec<PathBuf>,
     pended: usize,
     in_running: usize,
 
     abort: bool,
     stop: bool,
+    done: bool,
 }
 
 impl Queue {
@@ -229,101 +436,193 @@ impl Queue {
         Queue {
             chunk_size,
             parallels,
+            verify_attempts: 3,
+            retry: RetryPolicy::default(),
+            mirrors: Mirrors::default(),
             progress_sender,
             poll_duration,
             speed: 0.0,
             tasks: Vec::new(),
+            fallbacks: Vec::new(),
             client: Client::new(),
+            total: 0,
             completed: 0,
             failed: 0,
+            corrupt: Vec::new(),
             pended: 0,
             in_running: 0,
             abort: false,
             stop: false,
+            done: false,
         }
     }
     pub fn push_task(&mut self, task: Task) {
         self.tasks.push(task);
     }
+    // Tasks under `base_url` fall back to the same path under `alternate`
+    pub fn add_fallback(&mut self, base_url: &str, alternate: &str) {
+        self.fallbacks.push((base_url.to_owned(), alternate.to_owned()));
+    }
+    // Pause holds back chunks not yet started, Abort also cancels the running
+    // ones and removes every file left incomplete
+    pub fn control(&mut self, signal: ControlSignal) {
+        match signal {
+            ControlSignal::Pause => self.stop = true,
+            ControlSignal::Continue => self.stop = false,
+            ControlSignal::Abort => self.abort = true,
+        }
+    }
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+    pub fn is_paused(&self) -> bool {
+        self.stop
+    }
+    pub fn is_aborted(&self) -> bool {
+        self.abort
+    }
+    // Chunks to download, known once the queue has started
+    pub fn total(&self) -> usize {
+        self.total
+    }
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+    // Files still incomplete or failing their sha1 after `verify_attempts` fetches, they are removed
+    pub fn corrupt(&self) -> &[PathBuf] {
+        &self.corrupt
+    }
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
     pub fn run_in_background(self) -> Arc<Mutex<Queue>> {
         let lock = Arc::new(Mutex::new(self));
         let handle = lock.clone();
 
         thread::spawn(move || tokio_uring::start(async {
-            let mut queue = handle.lock();
-            let mut index = 0;
+            let mut verifiers: HashMap<PathBuf, Verifier> = HashMap::new();
+            let mut chunks = Vec::new();
+            let mut task_count = 0;
+
+            let (chunk_size, parallels) = {
+                let mut queue = handle.lock();
+                let chunk_size = queue.chunk_size;
+                let fallbacks = std::mem::take(&mut queue.fallbacks);
+                for mut task in merge_by_path(std::mem::take(&mut queue.tasks)) {
+                    let official = task.url.clone();
+                    let mirrored = queue.mirrors.rewrite(&official);
+                    if mirrored != official {
+                        task.url = mirrored;
+                        task.mirrors.insert(0, official.clone());
+                    }
+                    for (base_url, alternate) in &fallbacks {
+                        if let Some(rest) = official.strip_prefix(base_url.as_str()) {
+                            task.mirrors.push(format!("{}{}", alternate, rest));
+                        }
+                    }
+                    let split = task.clone().split(chunk_size);
+                    task_count += split.len();
+                    if task.sha1.is_none() && split.len() == 1 {
+                        chunks.extend(split);
+                        continue;
+                    }
 
-            let mut task_count = queue.tasks.len();
-            while index < task_count {
-                if queue.tasks[index].start + queue.chunk_size > queue.tasks[index].size || queue.tasks[index].size == 0 {
-                    index += 1;
-                    continue;
-                }
-                let mut task = queue.tasks[index].clone();
-                while task.start + queue.chunk_size < task.size {
-                    task.start += queue.chunk_size;
-                    queue.tasks.push(task.clone());
+                    let path = task.path.clone();
+                    let mut verifier = Verifier::new(task, split.len());
+                    let (resumed, verdict) = verifier.resume(chunk_size, &split);
+                    queue.completed += resumed.len();
+                    match verdict {
+                        Some(true) => continue,
+                        Some(false) => {
+                            queue.completed -= resumed.len();
+                            chunks.extend(verifier.retry(chunk_size));
+                        },
+                        None => chunks.extend(split.into_iter().filter(|chunk| !resumed.contains(&chunk.start))),
+                    }
+                    verifiers.insert(path, verifier);
                 }
-                index += 1;
-            }
-            task_count = queue.tasks.len();
+                queue.tasks = chunks;
+                queue.total = task_count;
+                (chunk_size, queue.parallels)
+            };
 
-            let (tx, mut rx) = mpsc::channel(queue.parallels);
+            let (tx, mut rx) = mpsc::channel::<(PathBuf, u64, Result<u64>)>(parallels);
 
             let mut stamp = Instant::now();
             let mut period_writed: u64 = 0;
-            let mut stop = false;
-
-            drop(queue);
+            let mut in_flight = HashMap::new();
 
             loop {
-                let queue = handle.lock();
-                if queue.abort || (task_count <= (queue.completed + queue.failed)) {
+                let (settled, poll_duration) = {
+                    let queue = handle.lock();
+                    (queue.abort || (task_count <= (queue.completed + queue.failed)), queue.poll_duration)
+                };
+                if settled {
                     break;
                 }
-                stop = queue.stop;
-                drop(queue);
 
                 tokio::select! {
-                    Some((path, res)) = rx.recv() => {
-                        if let Ok(size) = res {
+                    Some((path, start, res)) = rx.recv() => {
+                        in_flight.remove(&(path.clone(), start));
+                        // sent once the queue is unlocked, the receiver may be waiting on it
+                        let mut messages = Vec::new();
+                        let progress_sender = {
                             let mut queue = handle.lock();
-                            queue.completed += 1;
                             queue.in_running -= 1;
-                            if queue.progress_sender.is_some() {
-                                queue.progress_sender.as_ref().unwrap().send(Message::new(path, true, None, size)).await;
-                                period_writed += size;
+                            if let Ok(size) = res {
+                                queue.completed += 1;
+                                if queue.progress_sender.is_some() {
+                                    messages.push(Message::new(path.clone(), true, None, size));
+                                    period_writed += size;
+                                }
+
+                                let verdict = verifiers.get_mut(&path).and_then(|verifier| {
+                                    verifier.record(start, size);
+                                    verifier.land(start, size)
+                                });
+                                if verdict == Some(false) {
+                                    let verifier = verifiers.get_mut(&path).unwrap();
+                                    // the file's chunks count again once they are fetched anew
+                                    queue.completed -= verifier.chunks;
+                                    let reason = if verifier.attempts < queue.verify_attempts {
+                                        let retry = verifier.retry(chunk_size);
+                                        queue.tasks.extend(retry);
+                                        anyhow!("{} is damaged, downloading it again", path.display())
+                                    }
+                                    else {
+                                        let _ = std::fs::remove_file(&path);
+                                        verifier.discard_journal();
+                                        queue.failed += verifier.chunks;
+                                        queue.corrupt.push(path.clone());
+                                        anyhow!("{} is still damaged after {} attempts", path.display(), verifier.attempts)
+                                    };
+                                    messages.push(Message::new(path, false, Some(reason), 0));
+                                }
                             }
-                            drop(queue);
-                        }
-                        else if let Err(e) = res {
-                            let mut queue = handle.lock();
-                            queue.failed += 1;
-                            queue.in_running -= 1;
-                            println!("fail a  task!, in running: {}", queue.in_running);
-                            if queue.progress_sender.is_some() {
-                                queue.progress_sender.as_ref().unwrap().send(Message::new(path, false, Some(e), 0)).await;
+                            else if let Err(e) = res {
+                                queue.failed += 1;
+                                messages.push(Message::new(path, false, Some(e), 0));
+                            }
+                            queue.progress_sender.clone()
+                        };
+
+                        if let Some(progress_sender) = progress_sender {
+                            for message in messages {
+                                let _ = progress_sender.send(message).await;
                             }
-                            drop(queue);
                         }
                     },
-                    _ = async {
-                        let queue = handle.lock();
-                        let poll_duration = queue.poll_duration;
-                        drop(queue);
-
-                        sleep(poll_duration).await;
-                    }, if !stop => {
+                    _ = sleep(poll_duration) => {
                         let mut queue = handle.lock();
 
                         queue.speed = period_writed as f64 * (1.0 / stamp.elapsed().as_secs_f64());
                         stamp = Instant::now();
                         period_writed = 0;
 
-                        if queue.in_running < queue.parallels {
-                            if queue.tasks.len() == 0 {
-                                return;
-                            }
+                        if !queue.stop && queue.in_running < queue.parallels && !queue.tasks.is_empty() {
                             let n = std::cmp::min(queue.parallels - queue.in_running, queue.tasks.len());
                             for _ in 0..n {
                                 let len = queue.tasks.len();
@@ -331,12 +630,13 @@ impl Queue {
 
                                 let client = queue.client.clone();
                                 let sender = tx.clone();
-                                let chunk_size = queue.chunk_size;
+                                let policy = queue.retry.clone();
 
-                                tokio_uring::spawn(async move {
-                                    let res = task.download_part(client, chunk_size).await;
-                                    sender.send((task.path, res)).await
-                                });
+                                let (path, start) = (task.path.clone(), task.start);
+                                in_flight.insert((path.clone(), start), tokio_uring::spawn(async move {
+                                    let res = task.download_part_retrying(client, chunk_size, &policy).await;
+                                    sender.send((path, start, res)).await
+                                }));
                                 queue.in_running += 1;
                                 queue.pended += 1;
                             }
@@ -345,13 +645,42 @@ impl Queue {
                     }
                 }
             }
+            let mut queue = handle.lock();
+            if queue.abort {
+                let mut partial: Vec<PathBuf> = queue.tasks.drain(..).map(|task| task.path).collect();
+                for ((path, _), running) in in_flight.drain() {
+                    running.abort();
+                    partial.push(path);
+                }
+                partial.sort();
+                partial.dedup();
+                for path in partial.iter().filter(|path| path.is_file()) {
+                    let _ = std::fs::remove_file(path);
+                    Journal::for_file(path).remove();
+                }
+            }
+            queue.done = true;
+            drop(queue);
             drop(handle);
-            println!("download queue all done");
         }));
         lock
     }
 }
 
+// Resolves once a queue started by `run_in_background` has settled every task
+pub async fn wait(handle: &Arc<Mutex<Queue>>) {
+    loop {
+        let poll_duration = {
+            let queue = handle.lock();
+            if queue.done {
+                return;
+            }
+            queue.poll_duration
+        };
+        sleep(poll_duration).await;
+    }
+}
+
 // pub async fn download_libraries(version: &VersionConfig, condition: &Rule, dirpath: &str, progress_sender: &Sender<u32>, chunk_size: u64, parallels: u32) {
 //     let path_buf = PathBuf::from(&dirpath);
 //     let reqwest_client = reqwest::Client::new();
@@ -400,4 +729,81 @@ impl Queue {
 //                 stream::empty()
 //             }
 //         }));
-// }
\ No newline at end of file
+// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha1_of(data: &[u8]) -> String {
+        let mut hasher = sha1::Sha1::new();
+        hasher.update(data);
+        hasher.digest().to_string()
+    }
+
+    fn scratch_file(name: &str, data: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mc_launcher_core-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn splits_into_chunk_ranges() {
+        let starts: Vec<u64> = Task::new("http://host/file", "file", 10).split(4).iter().map(|chunk| chunk.start).collect();
+        assert_eq!(starts, [0, 4, 8]);
+        assert_eq!(Task::new("http://host/file", "file", 0).split(4).len(), 1);
+        assert_eq!(Task::new("http://host/file", "file", 4).split(4).len(), 1);
+    }
+
+    #[test]
+    fn merges_tasks_for_the_same_path() {
+        let merged = merge_by_path(vec![
+            Task::new("http://a/lib.jar", "lib.jar", 10),
+            Task::new("http://b/other.jar", "other.jar", 5),
+            Task::new("http://b/lib.jar", "lib.jar", 10).with_sha1("abc"),
+            Task::new("http://a/lib.jar", "lib.jar", 10),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].url, "http://a/lib.jar");
+        assert_eq!(merged[0].sha1.as_deref(), Some("abc"));
+        assert_eq!(merged[0].mirrors, ["http://b/lib.jar"]);
+    }
+
+    #[test]
+    fn verifies_chunks_landing_out_of_order() {
+        let data: Vec<u8> = (0..10u8).collect();
+        let path = scratch_file("out-of-order", &data);
+        let task = Task::new("http://host/file", &path, 10).with_sha1(&sha1_of(&data));
+        let mut verifier = Verifier::new(task, 3);
+
+        assert_eq!(verifier.land(8, 2), None);
+        assert_eq!(verifier.land(4, 4), None);
+        assert_eq!(verifier.land(0, 4), Some(true));
+    }
+
+    #[test]
+    fn rejects_a_mismatch_and_starts_over() {
+        let data: Vec<u8> = (0..10u8).collect();
+        let path = scratch_file("mismatch", &data);
+        let task = Task::new("http://host/file", &path, 10).with_sha1(&sha1_of(b"something else"));
+        let mut verifier = Verifier::new(task, 3);
+
+        verifier.land(0, 4);
+        verifier.land(4, 4);
+        assert_eq!(verifier.land(8, 2), Some(false));
+
+        let retry = verifier.retry(4);
+        assert_eq!(retry.len(), 3);
+        assert_eq!(verifier.attempts, 2);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn short_file_is_incomplete() {
+        let path = scratch_file("short", &[1, 2, 3]);
+        let mut verifier = Verifier::new(Task::new("http://host/file", &path, 10), 2);
+        verifier.land(0, 3);
+        assert_eq!(verifier.land(4, 6), Some(false));
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+use anyhow::Error;
+use rand::Rng;
+use tokio::time::Duration;
+
+// An HTTP error status, kept apart from network errors so the policy can tell
+// which ones are worth another try
+#[derive(Debug)]
+pub struct StatusError {
+    pub url: String,
+    pub status: u16,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} answered {}", self.url, self.status)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub attempts: u32, // tries of a chunk on each URL, the first one included
+    pub base_delay: Duration, // doubled after every failure
+    pub max_delay: Duration,
+    pub jitter: f64, // up to this fraction of a delay is taken off at random
+    pub retryable: Vec<u16>, // statuses worth retrying, network errors always are
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retryable: vec![408, 425, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Whether the same URL is worth another try. Other statuses, a 404 from a
+    // mirror missing the file say, go straight to the next URL
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error.downcast_ref::<StatusError>() {
+            Some(error) => self.retryable.contains(&error.status),
+            None => true,
+        }
+    }
+
+    // Wait before try `attempt` + 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+        let delay = exponential.min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        delay.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..=jitter))
+    }
+}
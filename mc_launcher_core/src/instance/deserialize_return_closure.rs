@@ -206,7 +206,7 @@ pub struct DownloadItem {
 
 impl Into<Task> for DownloadItem {
     fn into(self) -> Task {
-        Task::new(&self.url, &self.path, self.size)
+        Task::new(&self.url, &self.path, self.size).with_sha1(&self.sha1)
     }
 }
 
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Map, Value as Json};
+
+use crate::deserialize::MavenCoordinate;
+use super::Instance;
+
+// Load versions/<id>/<id>.json, following `inheritsFrom` up to the vanilla
+// profile and merging everything into a single instance
+pub fn load<P: AsRef<Path>>(versions_root: P, id: &str) -> Result<Instance> {
+    let profile = resolve(versions_root.as_ref(), id, &mut Vec::new())?;
+    serde_json::from_value(profile).map_err(anyhow::Error::new)
+}
+
+pub fn resolve_profile<P: AsRef<Path>>(versions_root: P, id: &str) -> Result<Json> {
+    resolve(versions_root.as_ref(), id, &mut Vec::new())
+}
+
+fn read_profile(versions_root: &Path, id: &str) -> Result<Json> {
+    let file = versions_root.join(id).join(format!("{}.json", id));
+    let reader = std::io::BufReader::new(std::fs::File::open(&file)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+fn resolve(versions_root: &Path, id: &str, chain: &mut Vec<String>) -> Result<Json> {
+    if chain.iter().any(|visited| visited == id) {
+        bail!("Version {} inherits from itself: {} -> {}", id, chain.join(" -> "), id);
+    }
+    chain.push(id.to_owned());
+
+    let mut child = read_profile(versions_root, id)?;
+    let parent_id = match child.get("inheritsFrom").and_then(|parent| parent.as_str()) {
+        Some(parent_id) => parent_id.to_owned(),
+        None => return Ok(child),
+    };
+    if !versions_root.join(&parent_id).join(format!("{}.json", parent_id)).is_file() {
+        bail!("Version {} inherits from {}, which isn't installed", id, parent_id);
+    }
+    let parent = resolve(versions_root, &parent_id, chain)?;
+
+    let child_map = child.as_object_mut().ok_or(anyhow!("Version {} isn't a JSON object", id))?;
+    child_map.remove("inheritsFrom");
+    merge(parent, std::mem::take(child_map))
+}
+
+fn merge(parent: Json, child: Map<String, Json>) -> Result<Json> {
+    let mut out = match parent {
+        Json::Object(map) => map,
+        _ => bail!("Parent version isn't a JSON object"),
+    };
+
+    // the client jar always comes from the root of the chain
+    if !child.contains_key("jar") {
+        if let Some(jar) = out.get("jar").or_else(|| out.get("id")).cloned() {
+            out.insert("jar".to_owned(), jar);
+        }
+    }
+
+    for (key, value) in child {
+        match key.as_str() {
+            "libraries" => {
+                let parent = out.remove("libraries").unwrap_or(Json::Array(Vec::new()));
+                out.insert(key, merge_libraries(parent, value));
+            },
+            "arguments" => {
+                let mut arguments = out.remove("arguments").unwrap_or(Json::Object(Map::new()));
+                if let (Some(arguments), Json::Object(child)) = (arguments.as_object_mut(), value) {
+                    for (side, list) in child {
+                        let entry = arguments.entry(side).or_insert(Json::Array(Vec::new()));
+                        if let (Some(entry), Json::Array(list)) = (entry.as_array_mut(), list) {
+                            entry.extend(list);
+                        }
+                    }
+                }
+                out.insert(key, arguments);
+            },
+            _ => {
+                out.insert(key, value);
+            },
+        }
+    }
+    Ok(Json::Object(out))
+}
+
+fn library_key(library: &Json) -> Option<String> {
+    let name = library.get("name")?.as_str()?;
+    Some(MavenCoordinate::parse(name).map_or(name.to_owned(), |coordinate| coordinate.key()))
+}
+
+// Child libraries first; parent libraries the child replaces are dropped
+fn merge_libraries(parent: Json, child: Json) -> Json {
+    let child = match child {
+        Json::Array(list) => list,
+        _ => Vec::new(),
+    };
+    let overridden: HashSet<String> = child.iter().filter_map(library_key).collect();
+
+    let mut out = child;
+    if let Json::Array(parent) = parent {
+        out.extend(parent.into_iter().filter(|library| {
+            library_key(library).is_none_or(|key| !overridden.contains(&key))
+        }));
+    }
+    Json::Array(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    fn write_profile(versions_root: &Path, profile: Json) {
+        let id = profile["id"].as_str().unwrap();
+        std::fs::create_dir_all(versions_root.join(id)).unwrap();
+        std::fs::write(versions_root.join(id).join(format!("{}.json", id)), profile.to_string()).unwrap();
+    }
+
+    fn names(profile: &Json) -> Vec<&str> {
+        profile["libraries"].as_array().unwrap().iter().map(|library| library["name"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn merges_a_chain_into_the_vanilla_profile() {
+        let root = scratch_dir("inherit-chain");
+        write_profile(&root, json!({
+            "id": "1.20.1",
+            "mainClass": "net.minecraft.client.main.Main",
+            "libraries": [{ "name": "org.ow2.asm:asm:9.3" }, { "name": "com.mojang:brigadier:1.1.8" }],
+            "arguments": { "game": ["--username"], "jvm": ["-cp", "${classpath}"] },
+        }));
+        write_profile(&root, json!({
+            "id": "fabric-loader-0.15.11-1.20.1",
+            "inheritsFrom": "1.20.1",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "libraries": [{ "name": "org.ow2.asm:asm:9.6" }, { "name": "net.fabricmc:fabric-loader:0.15.11" }],
+            "arguments": { "game": ["--fabric"], "jvm": ["-DFabricMcEmu=1"] },
+        }));
+        write_profile(&root, json!({
+            "id": "modpack",
+            "inheritsFrom": "fabric-loader-0.15.11-1.20.1",
+            "arguments": { "game": ["--modpack"] },
+        }));
+
+        let profile = resolve_profile(&root, "modpack").unwrap();
+        assert_eq!(profile["id"], "modpack");
+        assert_eq!(profile["jar"], "1.20.1");
+        assert_eq!(profile["mainClass"], "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        assert!(profile.get("inheritsFrom").is_none());
+        // the child's asm replaces vanilla's, unrelated parent libraries stay
+        assert_eq!(names(&profile), ["org.ow2.asm:asm:9.6", "net.fabricmc:fabric-loader:0.15.11", "com.mojang:brigadier:1.1.8"]);
+        // parent arguments come first
+        assert_eq!(profile["arguments"]["game"], json!(["--username", "--fabric", "--modpack"]));
+        assert_eq!(profile["arguments"]["jvm"], json!(["-cp", "${classpath}", "-DFabricMcEmu=1"]));
+    }
+
+    #[test]
+    fn keeps_libraries_with_another_classifier() {
+        let merged = merge_libraries(
+            json!([{ "name": "org.lwjgl:lwjgl:3.3.1" }, { "name": "org.lwjgl:lwjgl:3.3.1:natives-linux" }]),
+            json!([{ "name": "org.lwjgl:lwjgl:3.3.3" }]),
+        );
+        assert_eq!(names(&json!({ "libraries": merged })), ["org.lwjgl:lwjgl:3.3.3", "org.lwjgl:lwjgl:3.3.1:natives-linux"]);
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let root = scratch_dir("inherit-cycle");
+        write_profile(&root, json!({ "id": "a", "inheritsFrom": "b" }));
+        write_profile(&root, json!({ "id": "b", "inheritsFrom": "a" }));
+
+        let error = resolve_profile(&root, "a").unwrap_err().to_string();
+        assert_eq!(error, "Version a inherits from itself: a -> b -> a");
+    }
+
+    #[test]
+    fn needs_the_parent_installed() {
+        let root = scratch_dir("inherit-missing");
+        write_profile(&root, json!({ "id": "forge", "inheritsFrom": "1.20.1" }));
+
+        let error = resolve_profile(&root, "forge").unwrap_err().to_string();
+        assert_eq!(error, "Version forge inherits from 1.20.1, which isn't installed");
+    }
+}
@@ -1,30 +1,119 @@
 use serde::{Deserialize, de::{self, Visitor}};
 use serde_json::{Value as Json};
 
-use crate::deserialize::{LaunchArguments, AssetConfig,
+use crate::deserialize::{LaunchArguments, Argument, AssetConfig,
     MainDownloadItems, JavaVersion, Library, Logging};
 
+pub mod inherit;
+pub mod mods;
+pub mod mod_check;
+
 #[derive(Deserialize)]
+#[serde(from="VersionProfile")]
 pub struct Instance {
     pub arguments: LaunchArguments,
-    #[serde(rename="assetIndex")]
     pub assets_index: AssetConfig,
-    #[serde(rename="downloads")]
     pub main_downloads: MainDownloadItems,
 
-    #[serde(rename="id")]
     pub version: String,
-    #[serde(rename="javaVersion")]
+    pub version_type: String,
     pub java_version: JavaVersion,
+    pub jar: String, // id of the version whose client jar to launch
 
-    #[serde(deserialize_with="deserialize_skip_error")]
     pub libraries: Vec<Library>,
-    pub logging: Logging,
+    pub logging: Option<Logging>,
 
-    #[serde(rename="mainClass")]
     pub main_class: String,
 }
 
+// The version JSON as written. Before 1.13 there is no `arguments` object, just
+// a `minecraftArguments` string and no JVM arguments at all
+#[derive(Deserialize)]
+struct VersionProfile {
+    arguments: Option<LaunchArguments>,
+    #[serde(rename="minecraftArguments")]
+    minecraft_arguments: Option<String>,
+    #[serde(rename="assetIndex")]
+    assets_index: AssetConfig,
+    #[serde(rename="downloads")]
+    main_downloads: MainDownloadItems,
+
+    id: String,
+    #[serde(rename="type", default)]
+    version_type: String,
+    jar: Option<String>,
+    #[serde(rename="javaVersion", default)]
+    java_version: JavaVersion,
+
+    #[serde(deserialize_with="deserialize_skip_error")]
+    libraries: Vec<Library>,
+    logging: Option<Logging>,
+
+    #[serde(rename="mainClass")]
+    main_class: String,
+}
+
+impl From<VersionProfile> for Instance {
+    fn from(profile: VersionProfile) -> Instance {
+        let mut arguments = profile.arguments.unwrap_or_default();
+
+        if arguments.game.is_empty() {
+            if let Some(legacy) = &profile.minecraft_arguments {
+                arguments.game = legacy
+                    .split_whitespace()
+                    .map(|arg| Argument::Value(arg.to_owned()))
+                    .collect();
+            }
+        }
+        // Legacy profiles, and loader profiles stacked on them, don't pass the classpath themselves
+        let has_classpath = arguments.jvm.iter().any(|arg| match arg {
+            Argument::Value(value) => value.contains("${classpath}"),
+            Argument::Vec(values) => values.iter().any(|value| value.contains("${classpath}")),
+            Argument::None => false,
+        });
+        if !has_classpath {
+            let mut jvm = default_jvm_arguments();
+            jvm.append(&mut arguments.jvm);
+            arguments.jvm = jvm;
+        }
+
+        Instance {
+            arguments,
+            assets_index: profile.assets_index,
+            main_downloads: profile.main_downloads,
+            jar: profile.jar.unwrap_or_else(|| profile.id.clone()),
+            version: profile.id,
+            version_type: profile.version_type,
+            java_version: profile.java_version,
+            libraries: profile.libraries,
+            logging: profile.logging,
+            main_class: profile.main_class,
+        }
+    }
+}
+
+// What the official launcher passes to versions that don't list JVM arguments
+fn default_jvm_arguments() -> Vec<Argument> {
+    let mut out = Vec::new();
+    if cfg!(target_os="macos") {
+        out.push("-XstartOnFirstThread");
+    }
+    if cfg!(target_os="windows") {
+        out.push("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump");
+    }
+    if cfg!(target_arch="x86") {
+        out.push("-Xss1M");
+    }
+    out.extend(&[
+        "-Djava.library.path=${natives_directory}",
+        "-Dminecraft.launcher.brand=${launcher_name}",
+        "-Dminecraft.launcher.version=${launcher_version}",
+        "-cp",
+        "${classpath}",
+    ]);
+    out.into_iter().map(|arg| Argument::Value(arg.to_owned())).collect()
+}
+
 pub fn deserialize_skip_error<'de, D>(deserializer: D) -> Result<Vec<Library>, D::Error>
 where
         D: de::Deserializer<'de>, {
@@ -41,9 +130,8 @@ where
                 A: de::SeqAccess<'de>, {
             let mut out = Vec::new();
             while let Some(element) = seq.next_element::<Json>()? {
-                match serde_json::from_value::<Library>(element) {
-                    Ok(library) => out.push(library),
-                    Err(_) => {},
+                if let Ok(library) = serde_json::from_value::<Library>(element) {
+                    out.push(library);
                 }
             }
             Ok(out)
@@ -56,4 +144,81 @@ pub enum ReadResult {
     Fixed,
     Overflow,
     PartOverflow
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn values(args: &[Argument]) -> Vec<String> {
+        args.iter()
+            .flat_map(|arg| match arg {
+                Argument::Value(value) => vec![value.clone()],
+                Argument::Vec(values) => values.clone(),
+                Argument::None => Vec::new(),
+            })
+            .collect()
+    }
+
+    fn profile(id: &str, extra: Json) -> Instance {
+        let mut profile = json!({
+            "id": id,
+            "type": "release",
+            "mainClass": "net.minecraft.client.main.Main",
+            "assetIndex": { "id": "1.12", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "downloads": { "client": { "sha1": "", "size": 0, "url": "" } },
+            "libraries": [],
+        });
+        profile.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(profile).unwrap()
+    }
+
+    #[test]
+    fn splits_legacy_minecraft_arguments() {
+        let instance = profile("1.12.2", json!({
+            "minecraftArguments": "--username ${auth_player_name}  --version ${version_name} --gameDir ${game_directory}",
+        }));
+
+        assert_eq!(values(&instance.arguments.game), [
+            "--username", "${auth_player_name}", "--version", "${version_name}", "--gameDir", "${game_directory}",
+        ]);
+        let jvm = values(&instance.arguments.jvm);
+        assert_eq!(jvm[jvm.len() - 2..], ["-cp", "${classpath}"]);
+        assert!(jvm.contains(&"-Djava.library.path=${natives_directory}".to_owned()));
+        assert_eq!(instance.jar, "1.12.2");
+        assert_eq!(instance.java_version.major_version, 8);
+    }
+
+    #[test]
+    fn keeps_modern_arguments() {
+        let instance = profile("1.20.1", json!({
+            "arguments": {
+                "game": ["--username", "${auth_player_name}"],
+                "jvm": ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"],
+            },
+            "minecraftArguments": "--ignored",
+            "javaVersion": { "component": "java-runtime-gamma", "majorVersion": 17 },
+        }));
+
+        assert_eq!(values(&instance.arguments.game), ["--username", "${auth_player_name}"]);
+        assert_eq!(values(&instance.arguments.jvm), ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"]);
+        assert_eq!(instance.java_version.major_version, 17);
+    }
+
+    #[test]
+    fn adds_default_jvm_arguments_without_a_classpath() {
+        // loader profiles add JVM flags of their own but no classpath
+        let instance = profile("fabric-loader-0.15.11-1.20.1", json!({
+            "arguments": { "game": [], "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "] },
+            "jar": "1.20.1",
+        }));
+
+        let jvm = values(&instance.arguments.jvm);
+        assert_eq!(jvm.last().unwrap(), "-DFabricMcEmu= net.minecraft.client.main.Main ");
+        assert_eq!(jvm.iter().filter(|arg| *arg == "${classpath}").count(), 1);
+        assert!(jvm.iter().position(|arg| arg == "${classpath}").unwrap() < jvm.len() - 1);
+        assert_eq!(instance.jar, "1.20.1");
+    }
+}
@@ -0,0 +1,353 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::deserialize::{Library, MavenCoordinate};
+use crate::util::compare_versions;
+use super::mods::{DependencyKind, Environment, ModFile, ModInfo, ModLoader};
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum ModProblem {
+    Missing { mod_id: String, dependency: String, versions: Vec<String> },
+    VersionMismatch { mod_id: String, dependency: String, versions: Vec<String>, found: String, required: bool },
+    Breaks { mod_id: String, other: String, found: String },
+    Conflicts { mod_id: String, other: String, found: String },
+    Duplicate { mod_id: String, files: Vec<String> },
+    WrongLoader { file: String, loader: ModLoader },
+}
+
+impl ModProblem {
+    // Fatal problems stop the launch, the others are only shown
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            ModProblem::VersionMismatch { required, .. } => *required,
+            ModProblem::Conflicts { .. } => false,
+            _ => true,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ModProblem::Missing { mod_id, dependency, versions } =>
+                format!("{} needs {} {}", mod_id, dependency, describe(versions)),
+            ModProblem::VersionMismatch { mod_id, dependency, versions, found, .. } =>
+                format!("{} needs {} {}, but {} is installed", mod_id, dependency, describe(versions), found),
+            ModProblem::Breaks { mod_id, other, found } =>
+                format!("{} doesn't work with {} {}", mod_id, other, found),
+            ModProblem::Conflicts { mod_id, other, found } =>
+                format!("{} has known problems with {} {}", mod_id, other, found),
+            ModProblem::Duplicate { mod_id, files } =>
+                format!("{} is installed more than once: {}", mod_id, files.join(", ")),
+            ModProblem::WrongLoader { file, loader } =>
+                format!("{} is a {:?} mod and won't load here", file, loader),
+        }
+    }
+}
+
+fn describe(versions: &[String]) -> String {
+    if versions.is_empty() { "(any version)".to_owned() } else { versions.join(" or ") }
+}
+
+// The mod loader and its version, from the libraries of a version profile
+pub fn detect_loader(libraries: &[Library], minecraft: &str) -> Option<(ModLoader, String)> {
+    for library in libraries {
+        let coordinate = match MavenCoordinate::parse(&library.name) {
+            Some(coordinate) => coordinate,
+            None => continue,
+        };
+        let loader = match (coordinate.group.as_str(), coordinate.artifact.as_str()) {
+            ("org.quiltmc", "quilt-loader") => ModLoader::Quilt,
+            ("net.fabricmc", "fabric-loader") => ModLoader::Fabric,
+            ("net.neoforged", "neoforge") => ModLoader::NeoForge,
+            ("net.minecraftforge", "forge") | ("net.minecraftforge", "fmlloader") => ModLoader::Forge,
+            _ => continue,
+        };
+        // Forge versions carry the game version: 1.20.1-47.1.0, 1.7.10-10.13.4.1614-1.7.10
+        let version = coordinate.version
+            .trim_start_matches(&format!("{}-", minecraft))
+            .trim_end_matches(&format!("-{}", minecraft))
+            .to_owned();
+        let loader = match loader {
+            ModLoader::Forge if compare_versions(minecraft, "1.13") == Ordering::Less => ModLoader::LegacyForge,
+            loader => loader,
+        };
+        return Some((loader, version));
+    }
+    None
+}
+
+fn loads(instance: ModLoader, mod_loader: ModLoader) -> bool {
+    match instance {
+        ModLoader::Quilt => matches!(mod_loader, ModLoader::Quilt | ModLoader::Fabric),
+        // NeoForge for 1.20.1 still reads mods.toml
+        ModLoader::NeoForge => matches!(mod_loader, ModLoader::NeoForge | ModLoader::Forge),
+        ModLoader::Forge => mod_loader == ModLoader::Forge,
+        loader => mod_loader == loader,
+    }
+}
+
+// Ids the game and loader answer to themselves
+fn builtin_ids(minecraft: &str, loader: ModLoader, loader_version: &str) -> Vec<(String, String)> {
+    let mut ids = vec![("minecraft".to_owned(), minecraft.to_owned())];
+    let loader_ids: &[&str] = match loader {
+        ModLoader::Fabric => &["fabricloader"],
+        ModLoader::Quilt => &["quilt_loader", "fabricloader"],
+        ModLoader::Forge | ModLoader::LegacyForge => &["forge", "fml"],
+        ModLoader::NeoForge => &["neoforge", "forge", "fml"],
+    };
+    ids.extend(loader_ids.iter().map(|id| (id.to_string(), loader_version.to_owned())));
+    ids
+}
+
+// Check every enabled mod against the others, the loader and the game. Nothing
+// to check without a loader, vanilla ignores the mods folder
+pub fn check(files: &[ModFile], minecraft: &str, loader: Option<(ModLoader, &str)>) -> Vec<ModProblem> {
+    let (loader, loader_version) = match loader {
+        Some(loader) => loader,
+        None => return Vec::new(),
+    };
+    let mut problems = Vec::new();
+
+    let mut active: Vec<(&ModFile, &ModInfo)> = Vec::new();
+    for file in files.iter().filter(|file| file.enabled) {
+        if let Some(wrong) = file.mods.iter().find(|info| !loads(loader, info.loader)) {
+            problems.push(ModProblem::WrongLoader { file: file.file_name.clone(), loader: wrong.loader });
+            continue;
+        }
+        active.extend(file.mods.iter()
+            .filter(|info| info.environment != Environment::Server)
+            .map(|info| (file, info)));
+    }
+
+    // id -> every version available under it, and the files declaring it top level
+    let mut available: HashMap<String, Vec<String>> = HashMap::new();
+    let mut declared: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, version) in builtin_ids(minecraft, loader, loader_version) {
+        available.entry(id).or_default().push(version);
+    }
+    for (file, info) in &active {
+        declared.entry(info.id.to_lowercase()).or_default().push(file.file_name.clone());
+        for id in std::iter::once(&info.id).chain(info.provides.iter()) {
+            available.entry(id.to_lowercase()).or_default().push(info.version.clone());
+        }
+    }
+    for file in files.iter().filter(|file| file.enabled) {
+        for info in &file.bundled {
+            for id in std::iter::once(&info.id).chain(info.provides.iter()) {
+                available.entry(id.to_lowercase()).or_default().push(info.version.clone());
+            }
+        }
+    }
+
+    let mut duplicates: Vec<(&String, &Vec<String>)> = declared.iter()
+        .filter(|(_, files)| files.len() > 1)
+        .collect();
+    duplicates.sort();
+    for (id, files) in duplicates {
+        problems.push(ModProblem::Duplicate { mod_id: id.clone(), files: files.clone() });
+    }
+
+    for (_, info) in &active {
+        let maven_ranges = matches!(info.loader, ModLoader::Forge | ModLoader::NeoForge | ModLoader::LegacyForge);
+        for dependency in &info.dependencies {
+            let id = dependency.id.to_lowercase();
+            // Java is checked when the runtime is picked
+            if id == "java" || id == info.id.to_lowercase() {
+                continue;
+            }
+            let found = available.get(&id);
+            let matching = found.and_then(|versions| {
+                versions.iter()
+                    .filter(|version| matches_any(version, &dependency.versions, maven_ranges))
+                    .max_by(|a, b| compare(a, b))
+            });
+            let newest = found.and_then(|versions| versions.iter().max_by(|a, b| compare(a, b)));
+
+            match dependency.kind {
+                DependencyKind::Depends | DependencyKind::Recommends => match (newest, matching) {
+                    (None, _) if dependency.kind == DependencyKind::Depends => problems.push(ModProblem::Missing {
+                        mod_id: info.id.clone(),
+                        dependency: dependency.id.clone(),
+                        versions: dependency.versions.clone(),
+                    }),
+                    (Some(found), None) => problems.push(ModProblem::VersionMismatch {
+                        mod_id: info.id.clone(),
+                        dependency: dependency.id.clone(),
+                        versions: dependency.versions.clone(),
+                        found: found.clone(),
+                        // Forge holds optional dependencies to their range too
+                        required: dependency.kind == DependencyKind::Depends || maven_ranges,
+                    }),
+                    _ => {},
+                },
+                DependencyKind::Breaks | DependencyKind::Conflicts => {
+                    if let Some(found) = matching {
+                        let (mod_id, other, found) = (info.id.clone(), dependency.id.clone(), found.clone());
+                        problems.push(match dependency.kind {
+                            DependencyKind::Breaks => ModProblem::Breaks { mod_id, other, found },
+                            _ => ModProblem::Conflicts { mod_id, other, found },
+                        });
+                    }
+                },
+                DependencyKind::Suggests => {},
+            }
+        }
+    }
+    problems
+}
+
+// Build metadata doesn't order and trailing zeros of the release don't count:
+// 1.20 == 1.20.0, 1.0-beta < 1
+fn compare(a: &str, b: &str) -> Ordering {
+    let trim = |version: &str| {
+        let version = version.split('+').next().unwrap_or_default();
+        let (release, qualifier) = match version.find('-') {
+            Some(dash) => version.split_at(dash),
+            None => (version, ""),
+        };
+        let mut release = release.to_owned();
+        while release.ends_with(".0") {
+            release.truncate(release.len() - 2);
+        }
+        release + qualifier
+    };
+    compare_versions(&trim(a), &trim(b))
+}
+
+fn matches_any(version: &str, versions: &[String], maven_ranges: bool) -> bool {
+    versions.is_empty() || versions.iter().any(|spec| {
+        if maven_ranges { matches_maven_range(version, spec) } else { matches_predicates(version, spec) }
+    })
+}
+
+// Fabric/Quilt: space separated predicates, all of which must hold
+fn matches_predicates(version: &str, spec: &str) -> bool {
+    spec.split_whitespace().all(|predicate| matches_predicate(version, predicate))
+}
+
+fn matches_predicate(version: &str, predicate: &str) -> bool {
+    if predicate == "*" {
+        return true;
+    }
+    for op in &[">=", "<=", ">", "<", "=", "~", "^"] {
+        if let Some(target) = predicate.strip_prefix(op) {
+            let ordering = compare(version, target);
+            return match *op {
+                ">=" => ordering != Ordering::Less,
+                "<=" => ordering != Ordering::Greater,
+                ">" => ordering == Ordering::Greater,
+                "<" => ordering == Ordering::Less,
+                "=" => ordering == Ordering::Equal,
+                // ~1.2.3 is >=1.2.3 <1.3, ^1.2.3 is >=1.2.3 <2
+                _ => ordering != Ordering::Less && compare(version, &bump(target, if *op == "~" { 1 } else { 0 })) == Ordering::Less,
+            };
+        }
+    }
+    // 1.20.x
+    if let Some(prefix) = predicate.strip_suffix(".x").or_else(|| predicate.strip_suffix(".*")) {
+        return version == prefix || version.starts_with(&format!("{}.", prefix));
+    }
+    compare(version, predicate) == Ordering::Equal
+}
+
+// The smallest version above every one sharing `version`'s first `index + 1` parts
+fn bump(version: &str, index: usize) -> String {
+    let mut parts: Vec<u64> = version.split('.')
+        .map(|part| part.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap_or(0))
+        .collect();
+    parts.resize(std::cmp::max(parts.len(), index + 1), 0);
+    parts.truncate(index + 1);
+    parts[index] += 1;
+    parts.iter().map(|part| part.to_string()).collect::<Vec<String>>().join(".")
+}
+
+// Forge: maven ranges like [1.0,2.0) or (,1.2],[1.5,), a bare version matches anything
+fn matches_maven_range(version: &str, spec: &str) -> bool {
+    let spec = spec.trim();
+    if spec.is_empty() || spec == "*" || !(spec.starts_with('[') || spec.starts_with('(')) {
+        return true;
+    }
+
+    let mut rest = spec;
+    while let Some(start) = rest.find(['[', '(']) {
+        let end = match rest[start..].find([']', ')']) {
+            Some(end) => start + end,
+            None => return false,
+        };
+        let (open, close) = (&rest[start..start + 1], &rest[end..end + 1]);
+        let body = &rest[start + 1..end];
+
+        let matched = match body.split_once(',') {
+            None => compare(version, body.trim()) == Ordering::Equal,
+            Some((low, high)) => {
+                let (low, high) = (low.trim(), high.trim());
+                let above = low.is_empty() || match compare(version, low) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => open == "[",
+                    Ordering::Less => false,
+                };
+                let below = high.is_empty() || match compare(version, high) {
+                    Ordering::Less => true,
+                    Ordering::Equal => close == "]",
+                    Ordering::Greater => false,
+                };
+                above && below
+            },
+        };
+        if matched {
+            return true;
+        }
+        rest = &rest[end + 1..];
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fabric_predicates() {
+        assert!(matches_predicates("1.20.1", ">=1.20 <1.21"));
+        assert!(!matches_predicates("1.21", ">=1.20 <1.21"));
+        assert!(matches_predicates("1.20.0", "=1.20"));
+        assert!(matches_predicates("0.15.11+build.3", ">=0.15.11"));
+        assert!(matches_predicates("1.0-beta", "<1.0"));
+        assert!(matches_predicates("anything", "*"));
+
+        assert!(matches_predicates("1.20.4", "~1.20"));
+        assert!(!matches_predicates("1.21", "~1.20"));
+        assert!(matches_predicates("0.99", "^0.15.0"));
+        assert!(!matches_predicates("1.0.0", "^0.15.0"));
+
+        assert!(matches_predicates("1.20", "1.20.x"));
+        assert!(matches_predicates("1.20.4", "1.20.x"));
+        assert!(!matches_predicates("1.200", "1.20.x"));
+    }
+
+    #[test]
+    fn forge_maven_ranges() {
+        assert!(matches_maven_range("47.2.0", "[47,)"));
+        assert!(matches_maven_range("1.20.6", "[1.20,1.21)"));
+        assert!(!matches_maven_range("1.21", "[1.20,1.21)"));
+        assert!(matches_maven_range("1.12.2", "(,1.12.2]"));
+        assert!(!matches_maven_range("1.12.2", "(,1.12.2)"));
+        assert!(!matches_maven_range("1.20", "(1.20,)"));
+        assert!(matches_maven_range("1.0.0", "[1.0]"));
+
+        assert!(!matches_maven_range("1.3", "(,1.2],[1.5,)"));
+        assert!(matches_maven_range("1.6", "(,1.2],[1.5,)"));
+
+        assert!(matches_maven_range("1.0", "47.1.0"));
+        assert!(!matches_maven_range("1.0", "[1.0"));
+    }
+
+    #[test]
+    fn no_versions_match_anything() {
+        assert!(matches_any("1.0", &[], false));
+        assert!(matches_any("1.0", &[">=2".to_owned(), "1.x".to_owned()], false));
+        assert!(!matches_any("1.0", &["[2,)".to_owned()], true));
+    }
+}
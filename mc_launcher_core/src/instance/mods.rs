@@ -0,0 +1,527 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::Value as Json;
+
+pub const DISABLED_SUFFIX: &str = ".disabled";
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum ModLoader {
+    Fabric,
+    Quilt,
+    Forge,
+    NeoForge,
+    LegacyForge, // mcmod.info, 1.12.2 and older
+}
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum Environment {
+    Both,
+    Client,
+    Server,
+}
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum DependencyKind {
+    Depends,
+    Recommends, // optional, but the version has to match when present
+    Suggests,
+    Breaks,
+    Conflicts, // works, with problems
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ModDependency {
+    pub id: String,
+    pub kind: DependencyKind,
+    // Any of these has to match. Fabric and Quilt entries are space separated
+    // predicates (">=1.2 <2"), Forge ones maven ranges ("[1.2,2)")
+    pub versions: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ModInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub loader: ModLoader,
+    pub environment: Environment,
+    pub dependencies: Vec<ModDependency>,
+    pub provides: Vec<String>, // ids this mod also answers to
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ModFile {
+    pub path: PathBuf,
+    pub file_name: String,
+    pub enabled: bool,
+    pub mods: Vec<ModInfo>, // mods.toml may declare several
+    pub bundled: Vec<ModInfo>, // from jars nested inside, at any depth
+    pub error: Option<String>, // why the jar couldn't be read
+}
+
+// Nested jars inside nested jars are rare, deeper than this is a broken file
+const MAX_NESTING: usize = 4;
+
+// Every .jar and .jar.disabled in `mods_dir`, sorted by file name
+pub fn scan<P: AsRef<Path>>(mods_dir: P) -> Result<Vec<ModFile>> {
+    let entries = match std::fs::read_dir(mods_dir.as_ref()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut out = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let enabled = file_name.ends_with(".jar");
+        if !path.is_file() || !(enabled || file_name.ends_with(&format!(".jar{}", DISABLED_SUFFIX))) {
+            continue;
+        }
+
+        let (mods, bundled, error) = match read_mod(&path) {
+            Ok((mods, bundled)) => (mods, bundled, None),
+            Err(e) => (Vec::new(), Vec::new(), Some(e.to_string())),
+        };
+        out.push(ModFile { path, file_name, enabled, mods, bundled, error });
+    }
+    out.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(out)
+}
+
+// Rename between x.jar and x.jar.disabled. Returns the new path
+pub fn set_enabled<P: AsRef<Path>>(path: P, enabled: bool) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(anyhow!("Invalid mod file {}", path.display()))?;
+
+    let target = match (enabled, file_name.strip_suffix(DISABLED_SUFFIX)) {
+        (true, Some(name)) => path.with_file_name(name),
+        (false, None) if file_name.ends_with(".jar") => path.with_file_name(format!("{}{}", file_name, DISABLED_SUFFIX)),
+        (true, None) | (false, Some(_)) => return Ok(path.to_path_buf()),
+        (false, None) => bail!("{} isn't a jar", path.display()),
+    };
+    if target.exists() {
+        bail!("{} already exists", target.display());
+    }
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+// The mods a jar declares and the ones bundled in it
+pub fn read_mod<P: AsRef<Path>>(path: P) -> Result<(Vec<ModInfo>, Vec<ModInfo>)> {
+    read_archive(File::open(path.as_ref())?, 0)
+}
+
+fn read_archive<R: Read + Seek>(reader: R, depth: usize) -> Result<(Vec<ModInfo>, Vec<ModInfo>)> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+
+    let (mods, nested) = if let Some(text) = read_entry(&mut archive, "quilt.mod.json") {
+        let json: Json = serde_json::from_str(&text)?;
+        (vec![parse_quilt(&json)?], string_list(&json["quilt_loader"]["jars"]))
+    }
+    else if let Some(text) = read_entry(&mut archive, "fabric.mod.json") {
+        let json: Json = serde_json::from_str(&text)?;
+        let nested = json["jars"].as_array()
+            .map(|jars| jars.iter().map(|jar| string(&jar["file"])).collect())
+            .unwrap_or_default();
+        (vec![parse_fabric(&json)?], nested)
+    }
+    else if let Some((text, loader)) = read_entry(&mut archive, "META-INF/neoforge.mods.toml").map(|text| (text, ModLoader::NeoForge))
+        .or_else(|| read_entry(&mut archive, "META-INF/mods.toml").map(|text| (text, ModLoader::Forge))) {
+        let jar_version = read_entry(&mut archive, "META-INF/MANIFEST.MF")
+            .and_then(|manifest| manifest_value(&manifest, "Implementation-Version"));
+        // Forge's jar-in-jar
+        let nested = read_entry(&mut archive, "META-INF/jarjar/metadata.json")
+            .and_then(|text| serde_json::from_str::<Json>(&text).ok())
+            .and_then(|json| json["jars"].as_array().map(|jars| jars.iter().map(|jar| string(&jar["path"])).collect()))
+            .unwrap_or_default();
+        (parse_mods_toml(&text, loader, jar_version.as_deref())?, nested)
+    }
+    else if let Some(text) = read_entry(&mut archive, "mcmod.info") {
+        (parse_mcmod_info(&text)?, Vec::new())
+    }
+    else {
+        bail!("No mod metadata found")
+    };
+
+    let mut bundled = Vec::new();
+    if depth < MAX_NESTING {
+        for name in nested.iter().filter(|name| !name.is_empty()) {
+            let mut bytes = Vec::new();
+            match archive.by_name(name) {
+                Ok(mut entry) => entry.read_to_end(&mut bytes)?,
+                Err(_) => continue,
+            };
+            // plain libraries get bundled the same way, they just have no metadata
+            if let Ok((inner, inner_bundled)) = read_archive(Cursor::new(bytes), depth + 1) {
+                bundled.extend(inner);
+                bundled.extend(inner_bundled);
+            }
+        }
+    }
+    Ok((mods, bundled))
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, name: &str) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn manifest_value(manifest: &str, key: &str) -> Option<String> {
+    manifest.lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .map(|value| value.trim().to_owned())
+}
+
+fn string(value: &Json) -> String {
+    value.as_str().unwrap_or_default().to_owned()
+}
+
+// "1.2" or ["1.2", "1.3"]
+fn string_list(value: &Json) -> Vec<String> {
+    match value {
+        Json::String(s) => vec![s.clone()],
+        Json::Array(list) => list.iter().filter_map(|s| s.as_str().map(|s| s.to_owned())).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_fabric(json: &Json) -> Result<ModInfo> {
+    let id = json["id"].as_str().ok_or(anyhow!("fabric.mod.json has no id"))?.to_owned();
+
+    let mut dependencies = Vec::new();
+    for (key, kind) in &[
+        ("depends", DependencyKind::Depends),
+        ("recommends", DependencyKind::Recommends),
+        ("suggests", DependencyKind::Suggests),
+        ("breaks", DependencyKind::Breaks),
+        ("conflicts", DependencyKind::Conflicts),
+    ] {
+        if let Some(map) = json[*key].as_object() {
+            for (dependency, versions) in map {
+                dependencies.push(ModDependency { id: dependency.clone(), kind: *kind, versions: string_list(versions) });
+            }
+        }
+    }
+
+    Ok(ModInfo {
+        name: json["name"].as_str().unwrap_or(&id).to_owned(),
+        version: string(&json["version"]),
+        loader: ModLoader::Fabric,
+        environment: match json["environment"].as_str() {
+            Some("client") => Environment::Client,
+            Some("server") => Environment::Server,
+            _ => Environment::Both,
+        },
+        dependencies,
+        provides: string_list(&json["provides"]),
+        id,
+    })
+}
+
+// Quilt versions are a string, a list (any of) or {"any": [..]} / {"all": [..]}
+fn quilt_versions(value: &Json) -> Vec<String> {
+    match value {
+        Json::Object(map) => match (map.get("any"), map.get("all")) {
+            (Some(any), _) => any.as_array().map_or(Vec::new(), |list| list.iter().flat_map(quilt_versions).collect()),
+            (_, Some(all)) => vec![all.as_array().map_or(Vec::new(), |list| list.iter().flat_map(quilt_versions).collect()).join(" ")],
+            _ => Vec::new(),
+        },
+        value => string_list(value),
+    }
+}
+
+fn quilt_dependencies(value: &Json, kind: DependencyKind) -> Vec<ModDependency> {
+    let list = match value {
+        Json::Array(list) => list.clone(),
+        Json::Null => Vec::new(),
+        value => vec![value.clone()],
+    };
+    list.iter()
+        .filter_map(|dependency| match dependency {
+            Json::String(id) => Some(ModDependency { id: id.clone(), kind, versions: Vec::new() }),
+            Json::Object(map) => Some(ModDependency {
+                id: map.get("id")?.as_str()?.to_owned(),
+                kind: match map.get("optional").and_then(|optional| optional.as_bool()) {
+                    Some(true) if kind == DependencyKind::Depends => DependencyKind::Recommends,
+                    _ => kind,
+                },
+                versions: map.get("versions").map(quilt_versions).unwrap_or_default(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_quilt(json: &Json) -> Result<ModInfo> {
+    let loader = &json["quilt_loader"];
+    let id = loader["id"].as_str().ok_or(anyhow!("quilt.mod.json has no id"))?.to_owned();
+
+    let mut dependencies = quilt_dependencies(&loader["depends"], DependencyKind::Depends);
+    dependencies.extend(quilt_dependencies(&loader["breaks"], DependencyKind::Breaks));
+
+    let provides = match &loader["provides"] {
+        Json::Array(list) => list.iter()
+            .filter_map(|provided| provided.as_str().or_else(|| provided["id"].as_str()).map(|id| id.to_owned()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(ModInfo {
+        name: loader["metadata"]["name"].as_str().unwrap_or(&id).to_owned(),
+        version: string(&loader["version"]),
+        loader: ModLoader::Quilt,
+        environment: match json["minecraft"]["environment"].as_str() {
+            Some("client") => Environment::Client,
+            Some("dedicated_server") => Environment::Server,
+            _ => Environment::Both,
+        },
+        dependencies,
+        provides,
+        id,
+    })
+}
+
+fn parse_mods_toml(text: &str, loader: ModLoader, jar_version: Option<&str>) -> Result<Vec<ModInfo>> {
+    let toml: toml::Value = toml::from_str(text)?;
+    let toml_str = |value: Option<&toml::Value>| value.and_then(|value| value.as_str()).unwrap_or_default().to_owned();
+
+    let mods = toml.get("mods")
+        .and_then(|mods| mods.as_array())
+        .ok_or(anyhow!("mods.toml declares no mods"))?;
+
+    let mut out = Vec::new();
+    for declared in mods {
+        let id = toml_str(declared.get("modId"));
+        if id.is_empty() {
+            continue;
+        }
+        let mut version = toml_str(declared.get("version"));
+        if version == "${file.jarVersion}" {
+            version = jar_version.unwrap_or_default().to_owned();
+        }
+
+        let mut dependencies = Vec::new();
+        let declared_dependencies = toml.get("dependencies")
+            .and_then(|dependencies| dependencies.get(&id))
+            .and_then(|dependencies| dependencies.as_array());
+        for dependency in declared_dependencies.into_iter().flatten() {
+            // server only dependencies don't matter to a client
+            if toml_str(dependency.get("side")).eq_ignore_ascii_case("SERVER") {
+                continue;
+            }
+            // `mandatory` on Forge, `type` on NeoForge
+            let kind = match (dependency.get("type").and_then(|kind| kind.as_str()), dependency.get("mandatory").and_then(|mandatory| mandatory.as_bool())) {
+                (Some(kind), _) => match kind.to_ascii_lowercase().as_str() {
+                    "required" => DependencyKind::Depends,
+                    "incompatible" => DependencyKind::Breaks,
+                    "discouraged" => DependencyKind::Conflicts,
+                    _ => DependencyKind::Recommends,
+                },
+                (None, Some(false)) => DependencyKind::Recommends,
+                (None, _) => DependencyKind::Depends,
+            };
+            let range = toml_str(dependency.get("versionRange"));
+            dependencies.push(ModDependency {
+                id: toml_str(dependency.get("modId")),
+                kind,
+                versions: if range.is_empty() { Vec::new() } else { vec![range] },
+            });
+        }
+
+        out.push(ModInfo {
+            name: declared.get("displayName").and_then(|name| name.as_str()).unwrap_or(&id).to_owned(),
+            version,
+            loader,
+            environment: Environment::Both,
+            dependencies,
+            provides: Vec::new(),
+            id,
+        });
+    }
+    Ok(out)
+}
+
+// Either a bare list of mods or {"modListVersion": 2, "modList": [..]}
+fn parse_mcmod_info(text: &str) -> Result<Vec<ModInfo>> {
+    let json: Json = serde_json::from_str(text)?;
+    let list = match &json {
+        Json::Array(list) => list.clone(),
+        json => json["modList"].as_array().cloned().ok_or(anyhow!("mcmod.info has no mod list"))?,
+    };
+
+    let mut out = Vec::new();
+    for declared in list {
+        let id = string(&declared["modid"]);
+        if id.is_empty() {
+            continue;
+        }
+        // "modid" or "modid@[1.0,)"
+        let dependencies = string_list(&declared["requiredMods"]).into_iter()
+            .map(|required| match required.split_once('@') {
+                Some((id, range)) => ModDependency { id: id.to_owned(), kind: DependencyKind::Depends, versions: vec![range.to_owned()] },
+                None => ModDependency { id: required, kind: DependencyKind::Depends, versions: Vec::new() },
+            })
+            .collect();
+
+        out.push(ModInfo {
+            name: declared["name"].as_str().unwrap_or(&id).to_owned(),
+            version: string(&declared["version"]),
+            loader: ModLoader::LegacyForge,
+            environment: Environment::Both,
+            dependencies,
+            provides: Vec::new(),
+            id,
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{scratch_dir, write_zip};
+
+    fn file_named<'a>(files: &'a [ModFile], file_name: &str) -> &'a ModFile {
+        files.iter().find(|file| file.file_name == file_name).unwrap()
+    }
+
+    #[test]
+    fn reads_every_metadata_format() {
+        let dir = scratch_dir("mods-formats");
+        let inner = dir.join("inner.jar");
+        write_zip(&inner, &[("fabric.mod.json", br#"{"id": "fabric-api-base", "version": "0.4.31"}"#)]);
+        let inner = std::fs::read(&inner).unwrap();
+
+        write_zip(&dir.join("mods/sodium.jar"), &[
+            ("fabric.mod.json", br#"{
+                "id": "sodium", "name": "Sodium", "version": "0.5.8", "environment": "client",
+                "depends": { "minecraft": ["1.20.1"], "fabricloader": ">=0.12" },
+                "breaks": { "optifabric": "*" },
+                "jars": [{ "file": "META-INF/jars/base.jar" }]
+            }"#),
+            ("META-INF/jars/base.jar", &inner),
+        ]);
+        write_zip(&dir.join("mods/qsl.jar"), &[
+            ("quilt.mod.json", br#"{
+                "quilt_loader": {
+                    "id": "qsl", "version": "6.1.2", "metadata": { "name": "QSL" },
+                    "depends": ["quilt_loader", { "id": "minecraft", "versions": { "any": ["1.20", "1.20.1"] } }, { "id": "modmenu", "optional": true }],
+                    "provides": ["quilted_fabric_api"]
+                },
+                "minecraft": { "environment": "dedicated_server" }
+            }"#),
+        ]);
+        write_zip(&dir.join("mods/jei.jar"), &[
+            ("META-INF/mods.toml", br#"
+                modLoader = "javafml"
+                [[mods]]
+                modId = "jei"
+                displayName = "Just Enough Items"
+                version = "15.2.0"
+                [[dependencies.jei]]
+                modId = "forge"
+                mandatory = true
+                versionRange = "[47,)"
+                [[dependencies.jei]]
+                modId = "servercore"
+                mandatory = true
+                side = "SERVER"
+            "#),
+        ]);
+        write_zip(&dir.join("mods/old.jar.disabled"), &[
+            ("mcmod.info", br#"{"modListVersion": 2, "modList": [{"modid": "old", "version": "1.0", "requiredMods": ["Forge@[10.13,)"]}]}"#),
+        ]);
+
+        let files = scan(dir.join("mods")).unwrap();
+        let names: Vec<&str> = files.iter().map(|file| file.file_name.as_str()).collect();
+        assert_eq!(names, ["jei.jar", "old.jar.disabled", "qsl.jar", "sodium.jar"]);
+
+        let sodium = file_named(&files, "sodium.jar");
+        let info = &sodium.mods[0];
+        assert_eq!((info.id.as_str(), info.name.as_str(), info.version.as_str()), ("sodium", "Sodium", "0.5.8"));
+        assert_eq!(info.loader, ModLoader::Fabric);
+        assert_eq!(info.environment, Environment::Client);
+        let minecraft = info.dependencies.iter().find(|dependency| dependency.id == "minecraft").unwrap();
+        assert_eq!((minecraft.kind, minecraft.versions.clone()), (DependencyKind::Depends, vec!["1.20.1".to_owned()]));
+        assert!(info.dependencies.iter().any(|dependency| dependency.id == "optifabric" && dependency.kind == DependencyKind::Breaks));
+        assert_eq!(sodium.bundled.len(), 1);
+        assert_eq!(sodium.bundled[0].id, "fabric-api-base");
+
+        let qsl = &file_named(&files, "qsl.jar").mods[0];
+        assert_eq!((qsl.name.as_str(), qsl.loader, qsl.environment), ("QSL", ModLoader::Quilt, Environment::Server));
+        assert_eq!(qsl.provides, ["quilted_fabric_api"]);
+        let minecraft = qsl.dependencies.iter().find(|dependency| dependency.id == "minecraft").unwrap();
+        assert_eq!(minecraft.versions, ["1.20", "1.20.1"]);
+        assert!(qsl.dependencies.iter().any(|dependency| dependency.id == "modmenu" && dependency.kind == DependencyKind::Recommends));
+
+        let jei = &file_named(&files, "jei.jar").mods[0];
+        assert_eq!((jei.id.as_str(), jei.loader), ("jei", ModLoader::Forge));
+        assert_eq!(jei.dependencies.len(), 1);
+        assert_eq!(jei.dependencies[0].versions, ["[47,)"]);
+
+        let old = file_named(&files, "old.jar.disabled");
+        assert!(!old.enabled);
+        assert_eq!(old.mods[0].loader, ModLoader::LegacyForge);
+        assert_eq!(old.mods[0].dependencies[0].id, "Forge");
+        assert_eq!(old.mods[0].dependencies[0].versions, ["[10.13,)"]);
+    }
+
+    #[test]
+    fn falls_back_to_the_manifest_jar_version() {
+        let dir = scratch_dir("mods-jar-version");
+        let jar = dir.join("create.jar");
+        write_zip(&jar, &[
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\nImplementation-Version: 0.5.1.f\r\n"),
+            ("META-INF/mods.toml", b"[[mods]]\nmodId = \"create\"\nversion = \"${file.jarVersion}\"\n"),
+        ]);
+
+        let (mods, _) = read_mod(&jar).unwrap();
+        assert_eq!(mods[0].version, "0.5.1.f");
+    }
+
+    #[test]
+    fn malformed_metadata_doesnt_stop_the_scan() {
+        let dir = scratch_dir("mods-malformed");
+        write_zip(&dir.join("broken.jar"), &[("fabric.mod.json", b"{ not json")]);
+        write_zip(&dir.join("library.jar"), &[("a/B.class", b"")]);
+        std::fs::write(dir.join("truncated.jar"), b"PK").unwrap();
+        write_zip(&dir.join("fine.jar"), &[("fabric.mod.json", br#"{"id": "fine", "version": "1"}"#)]);
+        std::fs::write(dir.join("readme.txt"), b"").unwrap();
+
+        let files = scan(&dir).unwrap();
+        assert_eq!(files.len(), 4);
+        for name in ["broken.jar", "library.jar", "truncated.jar"] {
+            let file = file_named(&files, name);
+            assert!(file.mods.is_empty() && file.error.is_some(), "{}", name);
+        }
+        assert_eq!(file_named(&files, "fine.jar").mods[0].id, "fine");
+    }
+
+    #[test]
+    fn enables_and_disables_by_renaming() {
+        let dir = scratch_dir("mods-toggle");
+        let jar = dir.join("sodium.jar");
+        std::fs::write(&jar, b"jar").unwrap();
+
+        let disabled = set_enabled(&jar, false).unwrap();
+        assert_eq!(disabled, dir.join("sodium.jar.disabled"));
+        assert!(disabled.is_file() && !jar.exists());
+        assert_eq!(set_enabled(&disabled, false).unwrap(), disabled);
+
+        assert_eq!(set_enabled(&disabled, true).unwrap(), jar);
+        assert!(jar.is_file() && !disabled.exists());
+
+        std::fs::write(&disabled, b"other").unwrap();
+        assert!(set_enabled(&jar, false).is_err());
+        assert!(set_enabled(dir.join("notes.txt"), false).is_err());
+    }
+}
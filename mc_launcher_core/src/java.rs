@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug)]
+pub struct JavaRuntime {
+    pub home: PathBuf,
+    pub path: PathBuf, // the java executable
+    pub vendor: String,
+    pub version: String,
+    pub major_version: u32,
+    pub arch: String,
+}
+
+#[cfg(target_os="windows")]
+const JAVA_EXECUTABLE: &str = "java.exe";
+#[cfg(not(target_os="windows"))]
+const JAVA_EXECUTABLE: &str = "java";
+
+// Look through JAVA_HOME, PATH, the usual system locations, sdkman and our own
+// runtime directory
+pub fn discover<P: AsRef<Path>>(runtime_root: P) -> Vec<JavaRuntime> {
+    let mut homes: Vec<PathBuf> = Vec::new();
+
+    if let Some(home) = std::env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(home));
+    }
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            let executable = dir.join(JAVA_EXECUTABLE);
+            if !executable.is_file() {
+                continue;
+            }
+            // /usr/bin/java is usually a link into the real installation
+            let executable = executable.canonicalize().unwrap_or(executable);
+            if let Some(home) = executable.parent().and_then(|bin| bin.parent()) {
+                homes.push(home.to_path_buf());
+            }
+        }
+    }
+
+    let mut roots: Vec<PathBuf> = vec![runtime_root.as_ref().to_path_buf()];
+    if cfg!(target_os="windows") {
+        for var in &["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = std::env::var_os(var) {
+                let dir = PathBuf::from(dir);
+                roots.push(dir.join("Java"));
+                roots.push(dir.join("Eclipse Adoptium"));
+                roots.push(dir.join("Microsoft"));
+            }
+        }
+    } else if cfg!(target_os="macos") {
+        roots.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+    } else {
+        roots.push(PathBuf::from("/usr/lib/jvm"));
+        roots.push(PathBuf::from("/usr/java"));
+        roots.push(PathBuf::from("/opt/java"));
+    }
+    if let Some(user_home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        roots.push(PathBuf::from(user_home).join(".sdkman").join("candidates").join("java"));
+    }
+
+    for root in roots {
+        if let Ok(entries) = std::fs::read_dir(&root) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let home = entry.path();
+                // macOS bundles keep the actual home under Contents/Home,
+                // Mojang's runtimes under jre.bundle/Contents/Home
+                let bundled = [home.join("Contents").join("Home"), home.join("jre.bundle").join("Contents").join("Home")];
+                homes.push(bundled.iter().find(|dir| dir.is_dir()).cloned().unwrap_or(home));
+            }
+        }
+    }
+
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut out = Vec::new();
+    for home in homes {
+        let canonical = home.canonicalize().unwrap_or_else(|_| home.clone());
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+
+        if let Some(runtime) = inspect(&home) {
+            out.push(runtime);
+        }
+    }
+    out
+}
+
+pub fn inspect<P: AsRef<Path>>(home: P) -> Option<JavaRuntime> {
+    let home = home.as_ref();
+    let path = home.join("bin").join(JAVA_EXECUTABLE);
+    if !path.is_file() {
+        return None;
+    }
+
+    let mut properties = read_release(home);
+    if !["JAVA_VERSION", "IMPLEMENTOR", "OS_ARCH"].iter().all(|key| properties.contains_key(*key)) {
+        for (key, value) in query_java(&path)? {
+            properties.entry(key).or_insert(value);
+        }
+    }
+
+    let version = properties.remove("JAVA_VERSION")?;
+    Some(JavaRuntime {
+        home: home.to_path_buf(),
+        path,
+        vendor: properties.remove("IMPLEMENTOR").unwrap_or_default(),
+        major_version: major_version(&version)?,
+        version,
+        arch: normalize_arch(&properties.remove("OS_ARCH").unwrap_or_default()),
+    })
+}
+
+// Exact major version first, then the closest newer one. Runtimes built for
+// this machine's architecture win ties. Versions asking for Java 8 or older get
+// exactly 8, newer ones break their LaunchWrapper and Forge
+pub fn select(runtimes: &[JavaRuntime], major_version: u32) -> Option<&JavaRuntime> {
+    let native = normalize_arch(std::env::consts::ARCH);
+    let major_version = major_version.max(8);
+    let exact = major_version == 8;
+
+    runtimes.iter()
+        .filter(|runtime| if exact { runtime.major_version == 8 } else { runtime.major_version >= major_version })
+        .min_by_key(|runtime| (runtime.major_version - major_version, runtime.arch != native))
+}
+
+// The `release` file shipped in every JDK/JRE home: KEY="value" per line
+fn read_release(home: &Path) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    if let Ok(text) = std::fs::read_to_string(home.join("release")) {
+        for line in text.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                out.insert(key.trim().to_owned(), value.trim().trim_matches('"').to_owned());
+            }
+        }
+    }
+    out
+}
+
+fn query_java(path: &Path) -> Option<HashMap<String, String>> {
+    let output = Command::new(path)
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .ok()?;
+    // both settings and the version banner go to stderr
+    let text = String::from_utf8_lossy(&output.stderr);
+
+    let mut out = HashMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some((key, value)) = line.split_once(" = ") {
+            let key = match key {
+                "java.version" => "JAVA_VERSION",
+                "java.vendor" => "IMPLEMENTOR",
+                "os.arch" => "OS_ARCH",
+                _ => continue,
+            };
+            out.insert(key.to_owned(), value.to_owned());
+        }
+        // e.g. openjdk version "17.0.1" 2021-10-19
+        else if line.contains(" version \"") && !out.contains_key("JAVA_VERSION") {
+            if let Some(version) = line.split('"').nth(1) {
+                out.insert("JAVA_VERSION".to_owned(), version.to_owned());
+            }
+        }
+    }
+    Some(out)
+}
+
+// "1.8.0_312" -> 8, "17.0.1" -> 17
+pub fn major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse::<u32>().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+fn normalize_arch(arch: &str) -> String {
+    match arch {
+        "amd64" | "x86_64" | "x64" => "x86_64",
+        "x86" | "i386" | "i686" => "x86",
+        "aarch64" | "arm64" => "aarch64",
+        arch => arch,
+    }.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime(major_version: u32) -> JavaRuntime {
+        JavaRuntime {
+            home: PathBuf::from(format!("/jvm/{}", major_version)),
+            path: PathBuf::from(format!("/jvm/{}/bin/java", major_version)),
+            vendor: String::new(),
+            version: major_version.to_string(),
+            major_version,
+            arch: normalize_arch(std::env::consts::ARCH),
+        }
+    }
+
+    #[test]
+    fn picks_the_closest_newer_runtime() {
+        let runtimes = [runtime(8), runtime(21), runtime(17)];
+        assert_eq!(select(&runtimes, 17).unwrap().major_version, 17);
+        assert_eq!(select(&runtimes, 16).unwrap().major_version, 17);
+        assert!(select(&runtimes, 22).is_none());
+    }
+
+    #[test]
+    fn legacy_versions_only_take_java_8() {
+        assert_eq!(select(&[runtime(17), runtime(8)], 8).unwrap().major_version, 8);
+        assert_eq!(select(&[runtime(8)], 6).unwrap().major_version, 8);
+        assert!(select(&[runtime(11), runtime(17)], 8).is_none());
+    }
+}
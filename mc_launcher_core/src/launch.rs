@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+
+use crate::account::AccountInfo;
+use crate::deserialize::{Argument, Library};
+use crate::instance::Instance;
+use crate::util::{extract_zip, file_sha1, link_or_copy_dir};
+
+pub struct LaunchBuilder<'a> {
+    pub instance: &'a Instance,
+    pub account: &'a AccountInfo,
+    pub game_dir: PathBuf,
+
+    pub java_path: PathBuf,
+    pub assets_root: PathBuf,
+    pub library_root: PathBuf,
+    pub natives_dir: PathBuf,
+    pub classpath: String,
+
+    pub launcher_name: String,
+    pub launcher_version: String,
+    pub resolution: (u32, u32),
+    pub extra_jvm_args: Vec<String>,
+    pub logging_config: Option<PathBuf>, // downloaded `logging.client.file`
+}
+
+impl<'a> LaunchBuilder<'a> {
+    pub fn new<P>(instance: &'a Instance, account: &'a AccountInfo, game_dir: P) -> LaunchBuilder<'a>
+    where P: AsRef<std::path::Path>,
+    {
+        let game_dir = game_dir.as_ref().to_path_buf();
+        LaunchBuilder {
+            instance,
+            account,
+            java_path: PathBuf::from("java"),
+            assets_root: game_dir.join("assets"),
+            library_root: game_dir.join("libraries"),
+            natives_dir: game_dir
+                .join("versions")
+                .join(&instance.version)
+                .join(format!("natives-{}", Utc::now().timestamp_millis())),
+            classpath: String::new(),
+            launcher_name: env!("CARGO_PKG_NAME").to_owned(),
+            launcher_version: env!("CARGO_PKG_VERSION").to_owned(),
+            resolution: (854, 480),
+            extra_jvm_args: Vec::new(),
+            logging_config: None,
+            game_dir,
+        }
+    }
+
+    fn placeholders(&self) -> HashMap<&'static str, String> {
+        let mut map = HashMap::new();
+        let path = |p: &PathBuf| p.to_string_lossy().into_owned();
+
+        map.insert("auth_player_name", self.account.name.clone());
+        map.insert("auth_uuid", self.account.uuid.clone());
+        map.insert("auth_access_token", self.account.access_token.clone());
+        map.insert("auth_session", self.account.access_token.clone());
+        map.insert("auth_xuid", String::new());
+        map.insert("clientid", String::new());
+        map.insert("user_type", "msa".to_owned());
+        map.insert("user_properties", "{}".to_owned());
+
+        map.insert("version_name", self.instance.version.clone());
+        map.insert("version_type", self.instance.version_type.clone());
+        map.insert("game_directory", path(&self.game_dir));
+        map.insert("assets_root", path(&self.assets_root));
+        map.insert("game_assets", path(&self.assets_root));
+        map.insert("assets_index_name", self.instance.assets_index.id.clone());
+        map.insert("library_directory", path(&self.library_root));
+        map.insert("natives_directory", path(&self.natives_dir));
+        map.insert("classpath", self.classpath.clone());
+        map.insert("classpath_separator", CLASSPATH_SEPARATOR.to_owned());
+
+        map.insert("launcher_name", self.launcher_name.clone());
+        map.insert("launcher_version", self.launcher_version.clone());
+        map.insert("resolution_width", self.resolution.0.to_string());
+        map.insert("resolution_height", self.resolution.1.to_string());
+        map
+    }
+
+    pub fn build(&self) -> Result<Command> {
+        let placeholders = self.placeholders();
+
+        let mut command = Command::new(&self.java_path);
+        command.current_dir(&self.game_dir);
+
+        command.args(&self.extra_jvm_args);
+        for arg in expand_arguments(&self.instance.arguments.jvm, &placeholders)? {
+            command.arg(arg);
+        }
+        if let (Some(config), Some(logging)) = (&self.logging_config, &self.instance.logging) {
+            let mut map = HashMap::new();
+            map.insert("path", config.to_string_lossy().into_owned());
+            command.arg(substitute(&logging.client.argument, &map)?);
+        }
+        command.arg(&self.instance.main_class);
+        for arg in expand_arguments(&self.instance.arguments.game, &placeholders)? {
+            command.arg(arg);
+        }
+
+        Ok(command)
+    }
+}
+
+#[cfg(target_os="windows")]
+pub const CLASSPATH_SEPARATOR: &str = ";";
+#[cfg(not(target_os="windows"))]
+pub const CLASSPATH_SEPARATOR: &str = ":";
+
+fn expand_arguments(arguments: &[Argument], placeholders: &HashMap<&str, String>) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    for argument in arguments {
+        match argument {
+            Argument::Value(value) => out.push(substitute(value, placeholders)?),
+            Argument::Vec(values) => {
+                for value in values {
+                    out.push(substitute(value, placeholders)?);
+                }
+            },
+            Argument::None => {},
+        }
+    }
+    Ok(out)
+}
+
+// Replace every ${name} in `template`, failing on names we don't know about
+pub fn substitute(template: &str, placeholders: &HashMap<&str, String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(begin) = rest.find("${") {
+        out.push_str(&rest[..begin]);
+        let end = rest[begin..]
+            .find('}')
+            .ok_or(anyhow!("Unclosed placeholder in argument \"{}\"", template))?;
+
+        let name = &rest[begin + 2..begin + end];
+        match placeholders.get(name) {
+            Some(value) => out.push_str(value),
+            None => bail!("Unknown placeholder ${{{}}} in argument \"{}\"", name, template),
+        }
+        rest = &rest[begin + end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+// Extract every native jar into `natives_dir`. Each jar is unpacked once into
+// `cache_root/<sha1>` and linked from there on later launches
+pub fn extract_natives<P, Q, R>(libraries: &[Library], library_root: P, natives_dir: Q, cache_root: R) -> Result<()>
+where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        R: AsRef<Path>, {
+    let (library_root, natives_dir, cache_root) = (library_root.as_ref(), natives_dir.as_ref(), cache_root.as_ref());
+    std::fs::create_dir_all(natives_dir)?;
+
+    for library in libraries.iter().filter(|library| library.is_native) {
+        let jar = library_root.join(library.path());
+        if !jar.is_file() {
+            bail!("Native library {} is missing at {}", library.name, jar.display());
+        }
+
+        let sha1 = if library.download_item.sha1.is_empty() {
+            file_sha1(&jar)?
+        } else {
+            library.download_item.sha1.clone()
+        };
+
+        let cached = cache_root.join(&sha1);
+        if !cached.is_dir() {
+            let partial = cache_root.join(format!("{}.part", sha1));
+            if partial.exists() {
+                std::fs::remove_dir_all(&partial)?;
+            }
+            extract_zip(&jar, &partial, &library.extract_exclude)?;
+            std::fs::rename(&partial, &cached)?;
+        }
+
+        link_or_copy_dir(&cached, natives_dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize::{DownloadItem, LaunchArguments};
+    use crate::test_util::{scratch_dir, write_zip};
+
+    fn vanilla_placeholders() -> HashMap<&'static str, String> {
+        let mut map = HashMap::new();
+        for (name, value) in [
+            ("auth_player_name", "Steve"),
+            ("version_name", "1.20.1"),
+            ("game_directory", "/games/mc"),
+            ("assets_root", "/games/mc/assets"),
+            ("assets_index_name", "5"),
+            ("auth_uuid", "0123"),
+            ("auth_access_token", "token"),
+            ("clientid", ""),
+            ("auth_xuid", ""),
+            ("user_type", "msa"),
+            ("version_type", "release"),
+            ("resolution_width", "854"),
+            ("resolution_height", "480"),
+        ] {
+            map.insert(name, value.to_owned());
+        }
+        map
+    }
+
+    #[test]
+    fn expands_1_20_game_arguments() {
+        let arguments: LaunchArguments = serde_json::from_str(r#"{"game": [
+            "--username", "${auth_player_name}", "--version", "${version_name}", "--gameDir", "${game_directory}",
+            "--assetsDir", "${assets_root}", "--assetIndex", "${assets_index_name}", "--uuid", "${auth_uuid}",
+            "--accessToken", "${auth_access_token}", "--clientId", "${clientid}", "--xuid", "${auth_xuid}",
+            "--userType", "${user_type}", "--versionType", "${version_type}",
+            {"rules": [{"action": "allow", "features": {"is_demo_user": true}}], "value": "--demo"},
+            {"rules": [{"action": "allow", "features": {"has_custom_resolution": true}}],
+             "value": ["--width", "${resolution_width}", "--height", "${resolution_height}"]},
+            {"rules": [{"action": "allow", "features": {"has_quick_plays_support": true}}],
+             "value": ["--quickPlayPath", "${quickPlayPath}"]},
+            {"rules": [{"action": "allow", "features": {"is_quick_play_singleplayer": true}}],
+             "value": ["--quickPlaySingleplayer", "${quickPlaySingleplayer}"]},
+            {"rules": [{"action": "allow", "features": {"is_quick_play_multiplayer": true}}],
+             "value": ["--quickPlayMultiplayer", "${quickPlayMultiplayer}"]},
+            {"rules": [{"action": "allow", "features": {"is_quick_play_realms": true}}],
+             "value": ["--quickPlayRealms", "${quickPlayRealms}"]},
+            {"rules": [{"action": "allow", "features": {"some_future_feature": true}}], "value": "--future"}
+        ]}"#).unwrap();
+
+        let args = expand_arguments(&arguments.game, &vanilla_placeholders()).unwrap();
+        assert_eq!(args.len(), 22);
+        assert_eq!(&args[..4], ["--username", "Steve", "--version", "1.20.1"]);
+        assert!(!args.iter().any(|arg| arg == "--demo" || arg == "--width" || arg.starts_with("--quickPlay") || arg == "--future"));
+    }
+
+    #[test]
+    fn feature_asked_off_matches() {
+        let arguments: LaunchArguments = serde_json::from_str(r#"{"game": [
+            {"rules": [{"action": "allow", "features": {"is_demo_user": false}}], "value": "--full"}
+        ]}"#).unwrap();
+        assert_eq!(expand_arguments(&arguments.game, &HashMap::new()).unwrap(), ["--full"]);
+    }
+
+    #[test]
+    fn substitutes_placeholders() {
+        let placeholders = vanilla_placeholders();
+        assert_eq!(substitute("-Dname=${auth_player_name}", &placeholders).unwrap(), "-Dname=Steve");
+        assert_eq!(substitute("${version_name}-${version_type}", &placeholders).unwrap(), "1.20.1-release");
+        assert_eq!(substitute("plain", &placeholders).unwrap(), "plain");
+        assert_eq!(substitute("${clientid}", &placeholders).unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_unknown_and_unclosed_placeholders() {
+        let placeholders = vanilla_placeholders();
+        assert!(substitute("${quickPlayPath}", &placeholders).is_err());
+        assert!(substitute("--user ${auth_player_name", &placeholders).is_err());
+    }
+
+    fn native(name: &str, sha1: &str) -> Library {
+        Library {
+            download_item: DownloadItem { path: format!("{}.jar", name), sha1: sha1.to_owned(), size: 0, url: String::new() },
+            name: format!("org.lwjgl:{}:3.3.1:natives-linux", name),
+            is_native: true,
+            extract_exclude: vec!["META-INF/".to_owned()],
+        }
+    }
+
+    #[test]
+    fn extracts_natives_once_per_sha1() {
+        let dir = scratch_dir("launch-natives");
+        let (library_root, cache_root) = (dir.join("libraries"), dir.join("natives-cache"));
+        write_zip(&library_root.join("lwjgl.jar"), &[("META-INF/MANIFEST.MF", b""), ("liblwjgl.so", b"elf")]);
+        let libraries = [native("lwjgl", "abc123")];
+
+        extract_natives(&libraries, &library_root, dir.join("natives-1"), &cache_root).unwrap();
+        assert_eq!(std::fs::read(dir.join("natives-1").join("liblwjgl.so")).unwrap(), b"elf");
+        assert!(!dir.join("natives-1").join("META-INF").exists());
+        assert!(cache_root.join("abc123").join("liblwjgl.so").is_file());
+
+        // a second launch links the cached files instead of unpacking the jar again
+        std::fs::write(cache_root.join("abc123").join("liblwjgl.so"), b"cached").unwrap();
+        extract_natives(&libraries, &library_root, dir.join("natives-2"), &cache_root).unwrap();
+        assert_eq!(std::fs::read(dir.join("natives-2").join("liblwjgl.so")).unwrap(), b"cached");
+    }
+
+    #[test]
+    fn keys_the_cache_by_file_hash_when_none_is_given() {
+        let dir = scratch_dir("launch-natives-hash");
+        let library_root = dir.join("libraries");
+        write_zip(&library_root.join("glfw.jar"), &[("libglfw.so", b"elf")]);
+        let sha1 = file_sha1(library_root.join("glfw.jar")).unwrap();
+        // left behind by an interrupted extraction
+        std::fs::create_dir_all(dir.join("cache").join(format!("{}.part", sha1))).unwrap();
+        std::fs::write(dir.join("cache").join(format!("{}.part", sha1)).join("stale"), b"").unwrap();
+
+        extract_natives(&[native("glfw", "")], &library_root, dir.join("natives"), dir.join("cache")).unwrap();
+        assert!(dir.join("cache").join(&sha1).join("libglfw.so").is_file());
+        assert!(!dir.join("natives").join("stale").exists());
+    }
+
+    #[test]
+    fn needs_every_native_jar() {
+        let dir = scratch_dir("launch-natives-missing");
+        let error = extract_natives(&[native("openal", "")], dir.join("libraries"), dir.join("natives"), dir.join("cache")).unwrap_err();
+        assert!(error.to_string().starts_with("Native library org.lwjgl:openal:3.3.1:natives-linux is missing"));
+    }
+}
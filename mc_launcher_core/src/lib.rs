@@ -3,4 +3,16 @@ pub mod config;
 pub mod util;
 pub mod download;
 pub mod instance;
-pub mod deserialize;
\ No newline at end of file
+pub mod deserialize;
+pub mod launch;
+pub mod process;
+pub mod log4j;
+pub mod crash;
+pub mod java;
+pub mod runtime;
+pub mod loaders;
+pub mod modrinth;
+pub mod modpack;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::deserialize::Library;
+use super::install_profile;
+
+pub const FABRIC_META_URL: &str = "https://meta.fabricmc.net";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LoaderVersion {
+    pub separator: String,
+    pub build: u32,
+    pub maven: String,
+    pub version: String,
+    pub stable: bool,
+}
+
+#[derive(Deserialize)]
+struct LoaderEntry {
+    loader: LoaderVersion,
+}
+
+pub struct FabricMeta {
+    pub base_url: String,
+    client: Client,
+}
+
+impl FabricMeta {
+    pub fn new(base_url: &str) -> FabricMeta {
+        FabricMeta {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client: Client::new(),
+        }
+    }
+
+    // Newest first, as the meta server lists them
+    pub async fn loader_versions(&self, game_version: &str) -> Result<Vec<LoaderVersion>> {
+        let url = format!("{}/v2/versions/loader/{}", self.base_url, game_version);
+        let entries: Vec<LoaderEntry> = self.client.get(&url).send().await?.error_for_status()?.json().await?;
+
+        Ok(entries.into_iter().map(|entry| entry.loader).collect())
+    }
+
+    // Writes versions/<id>/<id>.json inheriting from `game_version`
+    pub async fn install<P>(&self, game_version: &str, loader_version: &str, versions_root: P) -> Result<(String, Vec<Library>)>
+    where P: AsRef<Path>,
+    {
+        let url = format!("{}/v2/versions/loader/{}/{}/profile/json", self.base_url, game_version, loader_version);
+        install_profile(&self.client, &url, versions_root).await
+    }
+}
+
+impl Default for FabricMeta {
+    fn default() -> Self {
+        FabricMeta::new(FABRIC_META_URL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use warp::Filter;
+
+    use super::*;
+
+    // A meta server knowing one loader build for 1.20.1
+    fn serve() -> FabricMeta {
+        let versions = warp::path!("v2" / "versions" / "loader" / String).map(|game_version: String| {
+            let entries = if game_version == "1.20.1" {
+                json!([{
+                    "loader": { "separator": ".", "build": 11, "maven": "net.fabricmc:fabric-loader:0.15.11", "version": "0.15.11", "stable": true },
+                    "intermediary": { "maven": "net.fabricmc:intermediary:1.20.1", "version": "1.20.1", "stable": true },
+                }])
+            } else {
+                json!([])
+            };
+            warp::reply::json(&entries)
+        });
+        let profile = warp::path!("v2" / "versions" / "loader" / "1.20.1" / "0.15.11" / "profile" / "json").map(|| {
+            warp::reply::json(&json!({
+                "id": "fabric-loader-0.15.11-1.20.1",
+                "inheritsFrom": "1.20.1",
+                "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+                "libraries": [
+                    {
+                        "name": "net.fabricmc:fabric-loader:0.15.11",
+                        "url": "https://maven.fabricmc.net/",
+                        "sha1": "6d8bbc3b6a3a6e3ed4ddcc5e8d4e9e5b5c1f2a7d",
+                        "size": 1346357,
+                    },
+                    { "name": "net.fabricmc:intermediary:1.20.1", "url": "https://maven.fabricmc.net/" },
+                ],
+            }))
+        });
+
+        let (addr, server) = warp::serve(profile.or(versions)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        FabricMeta::new(&format!("http://{}/", addr))
+    }
+
+    #[tokio::test]
+    async fn lists_loader_versions() {
+        let meta = serve();
+
+        let versions = meta.loader_versions("1.20.1").await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, "0.15.11");
+        assert!(versions[0].stable);
+
+        assert!(meta.loader_versions("1.0").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn installs_a_profile_with_checked_libraries() {
+        let meta = serve();
+        let versions_root = std::env::temp_dir().join(format!("mc_launcher_core-fabric-{}", std::process::id()));
+
+        let (id, libraries) = meta.install("1.20.1", "0.15.11", &versions_root).await.unwrap();
+        assert_eq!(id, "fabric-loader-0.15.11-1.20.1");
+        assert!(versions_root.join(&id).join(format!("{}.json", id)).is_file());
+
+        let loader = &libraries[0].download_item;
+        assert_eq!(loader.url, "https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar");
+        assert_eq!(loader.path, "net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar");
+        assert_eq!(loader.sha1, "6d8bbc3b6a3a6e3ed4ddcc5e8d4e9e5b5c1f2a7d");
+        assert_eq!(loader.size, 1346357);
+
+        // without a checksum the download is still queued, just unverified
+        let intermediary = &libraries[1].download_item;
+        assert!(intermediary.sha1.is_empty());
+        assert_eq!(intermediary.size, 0);
+
+        let _ = std::fs::remove_dir_all(&versions_root);
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Result, anyhow, bail};
+use serde_json::Value as Json;
+
+use crate::deserialize::{InstallProfile, Library, MavenCoordinate};
+use crate::download::Queue;
+use crate::launch::CLASSPATH_SEPARATOR;
+use crate::modpack::safe_join;
+use crate::util::file_sha1;
+use super::queue_libraries;
+
+pub const FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net";
+pub const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases";
+
+pub fn forge_installer_url(game_version: &str, forge_version: &str) -> String {
+    let mut version = format!("{}-{}", game_version, forge_version);
+    // 1.7.10 builds were published with the game version appended again,
+    // 1.7.10-10.13.4.1614-1.7.10
+    if game_version == "1.7.10" && !forge_version.ends_with("-1.7.10") {
+        version.push_str("-1.7.10");
+    }
+    format!("{}/net/minecraftforge/forge/{1}/forge-{1}-installer.jar", FORGE_MAVEN_URL, version)
+}
+
+pub fn neoforge_installer_url(game_version: &str, neoforge_version: &str) -> String {
+    // the 1.20.1 builds were still published as forge
+    if game_version == "1.20.1" {
+        let version = format!("{}-{}", game_version, neoforge_version.trim_start_matches("1.20.1-"));
+        return format!("{}/net/neoforged/forge/{1}/forge-{1}-installer.jar", NEOFORGE_MAVEN_URL, version);
+    }
+    format!("{}/net/neoforged/neoforge/{1}/neoforge-{1}-installer.jar", NEOFORGE_MAVEN_URL, neoforge_version)
+}
+
+// 1.12.2 and older installers keep an `install` block in install_profile.json
+pub fn is_legacy<P: AsRef<Path>>(installer: P) -> Result<bool> {
+    let mut archive = zip::ZipArchive::new(File::open(installer)?)?;
+    let profile: Json = serde_json::from_str(&read_entry(&mut archive, "install_profile.json")?)?;
+    Ok(profile.get("install").is_some())
+}
+
+// A 1.13+ Forge or NeoForge installer jar. Installing is two steps: `prepare`
+// writes the profile and queues libraries, `run_processors` runs once the queue
+// has finished
+pub struct ForgeInstaller {
+    pub installer: PathBuf,
+    pub profile: InstallProfile,
+    pub version_id: String,
+
+    version_json: Json,
+}
+
+impl ForgeInstaller {
+    pub fn open<P: AsRef<Path>>(installer: P) -> Result<ForgeInstaller> {
+        let installer = installer.as_ref().to_path_buf();
+        let mut archive = zip::ZipArchive::new(File::open(&installer)?)?;
+
+        let profile: Json = serde_json::from_str(&read_entry(&mut archive, "install_profile.json")?)?;
+        if profile.get("install").is_some() {
+            bail!("{} is a legacy Forge installer", installer.display());
+        }
+        let profile: InstallProfile = serde_json::from_value(profile)?;
+
+        let version_json: Json = serde_json::from_str(&read_entry(&mut archive, profile.json.trim_start_matches('/'))?)?;
+        let version_id = version_json["id"]
+            .as_str()
+            .ok_or(anyhow!("Version JSON in {} has no id", installer.display()))?
+            .to_owned();
+
+        Ok(ForgeInstaller { installer, profile, version_id, version_json })
+    }
+
+    // Write versions/<id>/<id>.json, unpack the libraries bundled under maven/
+    // and queue everything else both profiles list
+    pub fn prepare<P: AsRef<Path>>(&self, game_dir: P, queue: &mut Queue) -> Result<()> {
+        let game_dir = game_dir.as_ref();
+        let library_root = game_dir.join("libraries");
+
+        let version_dir = game_dir.join("versions").join(&self.version_id);
+        std::fs::create_dir_all(&version_dir)?;
+        std::fs::write(
+            version_dir.join(format!("{}.json", self.version_id)),
+            serde_json::to_string_pretty(&self.version_json)?)?;
+
+        let mut archive = zip::ZipArchive::new(File::open(&self.installer)?)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let relative = match entry.enclosed_name().and_then(|name| name.strip_prefix("maven").ok()) {
+                Some(relative) => relative.to_path_buf(),
+                None => continue,
+            };
+            let target = library_root.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::io::copy(&mut entry, &mut File::create(&target)?)?;
+        }
+
+        let version_libraries: Vec<Library> = self.version_json["libraries"]
+            .as_array()
+            .map(|libraries| {
+                libraries.iter()
+                    .filter_map(|library| serde_json::from_value(library.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // libraries without a url are bundled or produced by the processors
+        queue_libraries(&self.profile.libraries, &library_root, queue);
+        queue_libraries(&version_libraries, &library_root, queue);
+        Ok(())
+    }
+
+    pub fn run_processors<P, Q>(&self, game_dir: P, java: Q) -> Result<()>
+    where
+            P: AsRef<Path>,
+            Q: AsRef<Path>, {
+        let game_dir = game_dir.as_ref();
+        let library_root = game_dir.join("libraries");
+        let work_dir = game_dir.join(format!(".forge-installer-{}", self.version_id));
+
+        let data = self.data(game_dir, &work_dir)?;
+        let result = self.run_all(&library_root, &data, java.as_ref());
+
+        let _ = std::fs::remove_dir_all(&work_dir);
+        result
+    }
+
+    fn run_all(&self, library_root: &Path, data: &HashMap<String, String>, java: &Path) -> Result<()> {
+        for processor in &self.profile.processors {
+            if let Some(sides) = &processor.sides {
+                if !sides.iter().any(|side| side == "client") {
+                    continue;
+                }
+            }
+
+            let outputs = processor.outputs.iter()
+                .map(|(path, sha1)| Ok((replace_tokens(path, data, library_root)?, replace_tokens(sha1, data, library_root)?)))
+                .collect::<Result<Vec<(String, String)>>>()?;
+            // already produced by an earlier install
+            if !outputs.is_empty() && outputs.iter().all(|(path, sha1)| file_sha1(path).is_ok_and(|actual| &actual == sha1)) {
+                continue;
+            }
+
+            let jar = library_root.join(maven_path(&processor.jar)?);
+            let main_class = main_class(&jar)?;
+
+            let mut classpath = vec![jar.to_string_lossy().into_owned()];
+            for library in &processor.classpath {
+                classpath.push(library_root.join(maven_path(library)?).to_string_lossy().into_owned());
+            }
+            let args = processor.args.iter()
+                .map(|arg| replace_tokens(arg, data, library_root))
+                .collect::<Result<Vec<String>>>()?;
+
+            let status = Command::new(java)
+                .arg("-cp")
+                .arg(classpath.join(CLASSPATH_SEPARATOR))
+                .arg(&main_class)
+                .args(&args)
+                .status()?;
+            if !status.success() {
+                bail!("Processor {} failed with {}", processor.jar, status);
+            }
+
+            for (path, sha1) in &outputs {
+                let actual = file_sha1(path)?;
+                if &actual != sha1 {
+                    bail!("Processor {} produced {} with sha1 {}, expected {}", processor.jar, path, actual, sha1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Client side `data` plus the values every installer provides
+    fn data(&self, game_dir: &Path, work_dir: &Path) -> Result<HashMap<String, String>> {
+        let library_root = game_dir.join("libraries");
+        let minecraft = &self.profile.minecraft;
+        let path = |p: PathBuf| p.to_string_lossy().into_owned();
+
+        let mut data = HashMap::new();
+        data.insert("SIDE".to_owned(), "client".to_owned());
+        data.insert("MINECRAFT_VERSION".to_owned(), minecraft.clone());
+        data.insert("MINECRAFT_JAR".to_owned(), path(game_dir.join("versions").join(minecraft).join(format!("{}.jar", minecraft))));
+        data.insert("ROOT".to_owned(), path(game_dir.to_path_buf()));
+        data.insert("INSTALLER".to_owned(), path(self.installer.clone()));
+        data.insert("LIBRARY_DIR".to_owned(), path(library_root.clone()));
+
+        let mut archive = zip::ZipArchive::new(File::open(&self.installer)?)?;
+        for (key, value) in &self.profile.data {
+            let value = &value.client;
+            let resolved = if value.starts_with('[') && value.ends_with(']') {
+                path(library_root.join(maven_path(&value[1..value.len() - 1])?))
+            }
+            else if value.starts_with('\'') && value.ends_with('\'') && value.len() >= 2 {
+                value[1..value.len() - 1].to_owned()
+            }
+            else if value.starts_with('/') {
+                // a file shipped inside the installer, kept under `work_dir`
+                let target = safe_join(work_dir, value.trim_start_matches('/'))?;
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut entry = archive.by_name(value.trim_start_matches('/'))?;
+                std::io::copy(&mut entry, &mut File::create(&target)?)?;
+                path(target)
+            }
+            else {
+                value.clone()
+            };
+            data.insert(key.clone(), resolved);
+        }
+        Ok(data)
+    }
+}
+
+pub(super) fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Result<String> {
+    let mut entry = archive.by_name(name)?;
+    let mut out = String::new();
+    entry.read_to_string(&mut out)?;
+    Ok(out)
+}
+
+fn maven_path(name: &str) -> Result<PathBuf> {
+    MavenCoordinate::parse(name)
+        .map(|coordinate| coordinate.path())
+        .ok_or(anyhow!("Invalid maven name {}", name))
+}
+
+// {KEY} anywhere in the argument comes from `data`; an argument that is a whole
+// [maven name] becomes that library's path
+fn replace_tokens(arg: &str, data: &HashMap<String, String>, library_root: &Path) -> Result<String> {
+    if arg.starts_with('[') && arg.ends_with(']') {
+        return Ok(library_root.join(maven_path(&arg[1..arg.len() - 1])?).to_string_lossy().into_owned());
+    }
+
+    let mut out = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(begin) = rest.find('{') {
+        out.push_str(&rest[..begin]);
+        let end = rest[begin..].find('}').ok_or(anyhow!("Unclosed token in {}", arg))?;
+        let key = &rest[begin + 1..begin + end];
+        out.push_str(data.get(key).ok_or(anyhow!("Unknown processor data {{{}}}", key))?);
+        rest = &rest[begin + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn main_class(jar: &Path) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(File::open(jar)?)?;
+    let manifest = read_entry(&mut archive, "META-INF/MANIFEST.MF")?;
+
+    manifest.lines()
+        .find_map(|line| line.strip_prefix("Main-Class:"))
+        .map(|class| class.trim().to_owned())
+        .ok_or(anyhow!("{} has no Main-Class", jar.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::{scratch_dir, write_zip};
+
+    // An installer with one data value of each kind and a processor whose
+    // output is already in place
+    fn installer(dir: &Path, data_file: &str) -> ForgeInstaller {
+        let profile = json!({
+            "spec": 1,
+            "profile": "forge",
+            "version": "1.20.1-forge-47.2.0",
+            "minecraft": "1.20.1",
+            "json": "/version.json",
+            "data": {
+                "MAPPINGS": { "client": "[de.oceanlabs.mcp:mcp_config:1.20.1:mappings@txt]", "server": "" },
+                "MCP_VERSION": { "client": "'20230612.114412'", "server": "" },
+                "BINPATCH": { "client": data_file, "server": "" },
+            },
+            "processors": [{
+                "jar": "net.minecraftforge:binarypatcher:1.1.1",
+                "args": ["--output", "{PATCHED}"],
+                "outputs": { "{PATCHED}": "{PATCHED_SHA}" },
+            }],
+            "libraries": [],
+        });
+        let installer = dir.join("installer.jar");
+        write_zip(&installer, &[
+            ("install_profile.json", profile.to_string().as_bytes()),
+            ("version.json", br#"{"id": "1.20.1-forge-47.2.0"}"#),
+            ("data/client.lzma", b"patches"),
+        ]);
+        ForgeInstaller::open(&installer).unwrap()
+    }
+
+    #[test]
+    fn replaces_tokens() {
+        let data: HashMap<String, String> = [("SIDE".to_owned(), "client".to_owned()), ("ROOT".to_owned(), "/game".to_owned())].into_iter().collect();
+        let root = Path::new("/libraries");
+
+        assert_eq!(replace_tokens("--{SIDE}={ROOT}/x", &data, root).unwrap(), "--client=/game/x");
+        assert_eq!(
+            replace_tokens("[net.minecraft:client:1.20.1:slim]", &data, root).unwrap(),
+            root.join("net/minecraft/client/1.20.1/client-1.20.1-slim.jar").to_string_lossy(),
+        );
+        assert!(replace_tokens("{MISSING}", &data, root).is_err());
+        assert!(replace_tokens("{SIDE", &data, root).is_err());
+    }
+
+    #[test]
+    fn resolves_each_kind_of_data() {
+        let dir = scratch_dir("forge-data");
+        let work_dir = dir.join("work");
+        let data = installer(&dir, "/data/client.lzma").data(&dir, &work_dir).unwrap();
+
+        assert_eq!(data["MCP_VERSION"], "20230612.114412");
+        assert_eq!(
+            data["MAPPINGS"],
+            dir.join("libraries").join("de/oceanlabs/mcp/mcp_config/1.20.1/mcp_config-1.20.1-mappings.txt").to_string_lossy(),
+        );
+        let extracted = work_dir.join("data/client.lzma");
+        assert_eq!(data["BINPATCH"], extracted.to_string_lossy());
+        assert_eq!(std::fs::read(extracted).unwrap(), b"patches");
+        assert_eq!(data["SIDE"], "client");
+    }
+
+    #[test]
+    fn keeps_installer_files_inside_the_work_dir() {
+        let dir = scratch_dir("forge-escape");
+        let result = installer(&dir, "/../../data/client.lzma").data(&dir, &dir.join("work"));
+
+        assert!(result.is_err());
+        assert!(!dir.join("data").exists());
+    }
+
+    #[test]
+    fn skips_processors_whose_outputs_match() {
+        let dir = scratch_dir("forge-processors");
+        let installer = installer(&dir, "/data/client.lzma");
+        let patched = dir.join("patched.jar");
+        std::fs::write(&patched, b"done").unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("PATCHED".to_owned(), patched.to_string_lossy().into_owned());
+        data.insert("PATCHED_SHA".to_owned(), file_sha1(&patched).unwrap());
+        // the processor jar isn't there, so running it would fail
+        let java = dir.join("no-java");
+        installer.run_all(&dir.join("libraries"), &data, &java).unwrap();
+
+        data.insert("PATCHED_SHA".to_owned(), "0".repeat(40));
+        assert!(installer.run_all(&dir.join("libraries"), &data, &java).is_err());
+    }
+
+    #[test]
+    fn installer_urls() {
+        assert_eq!(
+            forge_installer_url("1.20.1", "47.2.0"),
+            "https://maven.minecraftforge.net/net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-installer.jar",
+        );
+        let legacy = "https://maven.minecraftforge.net/net/minecraftforge/forge/1.7.10-10.13.4.1614-1.7.10/forge-1.7.10-10.13.4.1614-1.7.10-installer.jar";
+        assert_eq!(forge_installer_url("1.7.10", "10.13.4.1614"), legacy);
+        assert_eq!(forge_installer_url("1.7.10", "10.13.4.1614-1.7.10"), legacy);
+    }
+
+    #[test]
+    fn neoforge_installer_urls() {
+        assert_eq!(
+            neoforge_installer_url("1.20.1", "47.1.106"),
+            "https://maven.neoforged.net/releases/net/neoforged/forge/1.20.1-47.1.106/forge-1.20.1-47.1.106-installer.jar",
+        );
+        assert_eq!(
+            neoforge_installer_url("1.21.1", "21.1.77"),
+            "https://maven.neoforged.net/releases/net/neoforged/neoforge/21.1.77/neoforge-21.1.77-installer.jar",
+        );
+    }
+}
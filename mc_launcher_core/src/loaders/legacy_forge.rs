@@ -0,0 +1,344 @@
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Result, anyhow, bail};
+use reqwest::Client;
+use serde_json::Value as Json;
+
+use crate::deserialize::{LegacyInstall, LegacyInstallProfile, Library, MavenCoordinate};
+use crate::download::{Mirrors, Queue};
+use crate::instance::inherit;
+use crate::java::JavaRuntime;
+use crate::util::file_sha1;
+use super::forge::{FORGE_MAVEN_URL, read_entry};
+use super::queue_libraries;
+
+pub const MOJANG_LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
+// where the old installers point, long gone
+const OLD_FORGE_MAVEN_URL: &str = "files.minecraftforge.net/maven";
+
+#[cfg(target_os="windows")]
+const UNPACK200_EXECUTABLE: &str = "unpack200.exe";
+#[cfg(not(target_os="windows"))]
+const UNPACK200_EXECUTABLE: &str = "unpack200";
+
+// A 1.7.10 - 1.12.2 installer: the version JSON comes straight from
+// `versionInfo` and the universal jar is copied into libraries/, no processors
+pub struct LegacyForgeInstaller {
+    pub installer: PathBuf,
+    pub install: LegacyInstall,
+    pub version_id: String,
+
+    version_info: Json,
+    libraries: Vec<(PathBuf, String, Vec<String>)>, // path, url, sha1 checksums
+}
+
+impl LegacyForgeInstaller {
+    pub fn open<P: AsRef<Path>>(installer: P) -> Result<LegacyForgeInstaller> {
+        let installer = installer.as_ref().to_path_buf();
+        let mut archive = zip::ZipArchive::new(File::open(&installer)?)?;
+
+        let profile: LegacyInstallProfile = serde_json::from_str(&read_entry(&mut archive, "install_profile.json")?)?;
+        let version_id = profile.version_info["id"]
+            .as_str()
+            .unwrap_or(&profile.install.target)
+            .to_owned();
+
+        Ok(LegacyForgeInstaller {
+            installer,
+            install: profile.install,
+            version_id,
+            version_info: profile.version_info,
+            libraries: Vec::new(),
+        })
+    }
+
+    // Write versions/<id>/<id>.json inheriting from the vanilla version, copy the
+    // universal jar and queue the other libraries. The vanilla version has to be
+    // installed already
+    pub fn prepare<P: AsRef<Path>>(&mut self, game_dir: P, queue: &mut Queue) -> Result<()> {
+        let game_dir = game_dir.as_ref();
+        let versions_root = game_dir.join("versions");
+        let library_root = game_dir.join("libraries");
+        let minecraft = &self.install.minecraft;
+
+        let parent = inherit::resolve_profile(&versions_root, minecraft)
+            .map_err(|_| anyhow!("Minecraft {} has to be installed first", minecraft))?;
+        let parent_keys: HashSet<String> = parent["libraries"]
+            .as_array()
+            .map(|libraries| libraries.iter().filter_map(library_key).collect())
+            .unwrap_or_default();
+
+        let mut version = self.version_info.clone();
+        let map = version.as_object_mut().ok_or(anyhow!("versionInfo isn't a JSON object"))?;
+        // 1.7.10 profiles are complete copies of the vanilla one; inherit instead
+        // so natives, assets and the client jar come from the installed version
+        let standalone = !map.contains_key("inheritsFrom");
+        if standalone {
+            map.insert("inheritsFrom".to_owned(), Json::String(minecraft.clone()));
+            map.insert("jar".to_owned(), Json::String(minecraft.clone()));
+        }
+
+        let mut libraries = Vec::new();
+        self.libraries.clear();
+        for mut library in map.remove("libraries").and_then(|list| list.as_array().cloned()).unwrap_or_default() {
+            let key = match library_key(&library) {
+                Some(key) => key,
+                None => continue,
+            };
+            // a client install skips server-only libraries (serverreq without
+            // clientreq, or clientreq false) and keeps unflagged ones
+            let (client, server) = (library["clientreq"].as_bool(), library["serverreq"].as_bool());
+            if client == Some(false) || (client.is_none() && server == Some(true)) {
+                continue;
+            }
+            let has_url = library.get("url").and_then(|url| url.as_str()).is_some_and(|url| !url.is_empty());
+            if standalone && !has_url && parent_keys.contains(&key) {
+                continue;
+            }
+
+            let name = library["name"].as_str().unwrap_or_default().to_owned();
+            let repository = match library.get("url").and_then(|url| url.as_str()) {
+                Some(url) if !url.is_empty() && url.contains(OLD_FORGE_MAVEN_URL) => format!("{}/", FORGE_MAVEN_URL),
+                Some(url) if !url.is_empty() => url.to_owned(),
+                _ => MOJANG_LIBRARIES_URL.to_owned(),
+            };
+            library["url"] = Json::String(repository.clone());
+
+            if let Some(coordinate) = MavenCoordinate::parse(&name) {
+                let checksums = library["checksums"]
+                    .as_array()
+                    .map(|list| list.iter().filter_map(|sha1| sha1.as_str().map(|sha1| sha1.to_owned())).collect())
+                    .unwrap_or_default();
+                if name != self.install.path {
+                    self.libraries.push((library_root.join(coordinate.path()), coordinate.url(&repository), checksums));
+                }
+            }
+            libraries.push(library);
+        }
+        map.insert("libraries".to_owned(), Json::Array(libraries.clone()));
+        map.insert("id".to_owned(), Json::String(self.version_id.clone()));
+
+        let version_dir = versions_root.join(&self.version_id);
+        std::fs::create_dir_all(&version_dir)?;
+        std::fs::write(version_dir.join(format!("{}.json", self.version_id)), serde_json::to_string_pretty(&version)?)?;
+
+        let universal = MavenCoordinate::parse(&self.install.path)
+            .ok_or(anyhow!("Invalid maven name {}", self.install.path))?;
+        let target = library_root.join(universal.path());
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut archive = zip::ZipArchive::new(File::open(&self.installer)?)?;
+        std::io::copy(&mut archive.by_name(&self.install.file_path)?, &mut File::create(&target)?)?;
+
+        let libraries: Vec<Library> = libraries.into_iter()
+            .filter_map(|library| serde_json::from_value(library).ok())
+            .collect();
+        queue_libraries(&libraries, &library_root, queue);
+        Ok(())
+    }
+
+    // After the queue: fetch <jar>.pack.xz for every library the plain download
+    // didn't produce (Forge's maven only has those for some), then check them all
+    pub async fn finish(&self, client: &Client, mirrors: &Mirrors, unpack200: Option<&Path>) -> Result<()> {
+        let mut broken = Vec::new();
+        for (path, url, checksums) in &self.libraries {
+            if intact(path, checksums) {
+                continue;
+            }
+            let unpacked = match unpack200 {
+                Some(unpack200) => fetch_packed(client, mirrors, url, path, unpack200).await,
+                None => Err(anyhow!("unpack200 from a Java 8 runtime is needed")),
+            };
+            if let Err(e) = unpacked.and_then(|_| if intact(path, checksums) { Ok(()) } else { Err(anyhow!("checksum mismatch")) }) {
+                broken.push(format!("{} ({})", path.display(), e));
+            }
+        }
+        if !broken.is_empty() {
+            bail!("{} libraries couldn't be installed: {}", broken.len(), broken.join(", "));
+        }
+        Ok(())
+    }
+}
+
+// unpack200 was dropped in Java 14
+pub fn find_unpack200(runtimes: &[JavaRuntime]) -> Option<PathBuf> {
+    runtimes.iter()
+        .map(|runtime| runtime.home.join("bin").join(UNPACK200_EXECUTABLE))
+        .find(|path| path.is_file())
+}
+
+fn library_key(library: &Json) -> Option<String> {
+    let name = library.get("name")?.as_str()?;
+    Some(MavenCoordinate::parse(name).map_or(name.to_owned(), |coordinate| coordinate.key()))
+}
+
+// A readable jar matching one of the checksums, if the profile lists any
+fn intact(path: &Path, checksums: &[String]) -> bool {
+    let readable = File::open(path).ok().is_some_and(|file| zip::ZipArchive::new(file).is_ok());
+    readable && (checksums.is_empty() || file_sha1(path).is_ok_and(|sha1| checksums.contains(&sha1)))
+}
+
+// pack200 data, then the jar's signature checksums, their length and "SIGN"
+fn pack200_data(data: &[u8]) -> Result<&[u8]> {
+    let len = data.len();
+    if len < 8 || &data[len - 4..] != b"SIGN" {
+        bail!("has no signature trailer");
+    }
+    let signature_len = u32::from_le_bytes(data[len - 8..len - 4].try_into()?) as usize;
+    let pack_len = len.checked_sub(8 + signature_len).ok_or(anyhow!("is truncated"))?;
+    Ok(&data[..pack_len])
+}
+
+async fn fetch_packed(client: &Client, mirrors: &Mirrors, url: &str, jar: &Path, unpack200: &Path) -> Result<()> {
+    let packed_url = format!("{}.pack.xz", url);
+    let mirrored = mirrors.rewrite(&packed_url);
+    // the official maven when the mirror lacks it, as the queue does
+    let packed = match client.get(&mirrored).send().await.and_then(|response| response.error_for_status()) {
+        Ok(response) => response.bytes().await?,
+        Err(_) if mirrored != packed_url => client.get(&packed_url).send().await?.error_for_status()?.bytes().await?,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut data = Vec::new();
+    lzma_rs::xz_decompress(&mut std::io::Cursor::new(&packed[..]), &mut data)
+        .map_err(|e| anyhow!("Can't decompress {}.pack.xz: {:?}", url, e))?;
+
+    let pack200 = pack200_data(&data).map_err(|e| anyhow!("{}.pack.xz {}", url, e))?;
+
+    if let Some(parent) = jar.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let pack = jar.with_extension("jar.pack");
+    std::fs::write(&pack, pack200)?;
+    let status = Command::new(unpack200).arg(&pack).arg(jar).status();
+    let _ = std::fs::remove_file(&pack);
+
+    if !status?.success() {
+        bail!("unpack200 failed on {}.pack.xz", url);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::time::Duration;
+
+    use super::*;
+    use crate::test_util::{scratch_dir, write_zip};
+
+    const FORGE: &str = "net.minecraftforge:forge:1.7.10-10.13.4.1614-1.7.10";
+    const VERSION_ID: &str = "1.7.10-Forge10.13.4.1614-1.7.10";
+
+    fn prepared(game_dir: &Path) -> (LegacyForgeInstaller, Json) {
+        let vanilla = json!({
+            "id": "1.7.10",
+            "minecraftArguments": "--username ${auth_player_name}",
+            "libraries": [{ "name": "com.google.guava:guava:17.0" }],
+        });
+        let vanilla_dir = game_dir.join("versions/1.7.10");
+        std::fs::create_dir_all(&vanilla_dir).unwrap();
+        std::fs::write(vanilla_dir.join("1.7.10.json"), vanilla.to_string()).unwrap();
+
+        let profile = json!({
+            "install": { "target": VERSION_ID, "path": FORGE, "filePath": "forge-universal.jar", "minecraft": "1.7.10" },
+            "versionInfo": {
+                "id": VERSION_ID,
+                "minecraftArguments": "--username ${auth_player_name} --tweakClass cpw.mods.fml.common.launcher.FMLTweaker",
+                "mainClass": "net.minecraft.launchwrapper.Launch",
+                "libraries": [
+                    { "name": FORGE, "url": "http://files.minecraftforge.net/maven/" },
+                    { "name": "net.minecraft:launchwrapper:1.12", "clientreq": true, "serverreq": true },
+                    { "name": "com.typesafe.akka:akka-actor_2.11:2.3.3", "url": "http://files.minecraftforge.net/maven/", "checksums": ["ed62e9fc709ca0f2ff1a3220daa8b70a2870078e"], "clientreq": true },
+                    { "name": "com.google.guava:guava:17.0" },
+                    { "name": "org.ow2.asm:asm-all:5.0.3" },
+                    { "name": "lzma:lzma:0.0.1", "serverreq": true },
+                    { "name": "java3d:vecmath:1.3.1", "clientreq": false, "serverreq": true },
+                ],
+            },
+        });
+        let installer = game_dir.join("installer.jar");
+        write_zip(&installer, &[
+            ("install_profile.json", profile.to_string().as_bytes()),
+            ("forge-universal.jar", b"universal"),
+        ]);
+
+        let mut installer = LegacyForgeInstaller::open(&installer).unwrap();
+        let mut queue = Queue::new(1024, 1, None, Duration::from_millis(10));
+        installer.prepare(game_dir, &mut queue).unwrap();
+
+        let version_file = game_dir.join("versions").join(VERSION_ID).join(format!("{}.json", VERSION_ID));
+        let version = serde_json::from_str(&std::fs::read_to_string(version_file).unwrap()).unwrap();
+        (installer, version)
+    }
+
+    #[test]
+    fn rewrites_version_info() {
+        let game_dir = scratch_dir("legacy-forge");
+        let (installer, version) = prepared(&game_dir);
+
+        assert_eq!(installer.version_id, VERSION_ID);
+        assert_eq!(version["id"], VERSION_ID);
+        assert_eq!(version["inheritsFrom"], "1.7.10");
+        assert_eq!(version["jar"], "1.7.10");
+        assert_eq!(version["minecraftArguments"], "--username ${auth_player_name} --tweakClass cpw.mods.fml.common.launcher.FMLTweaker");
+
+        let libraries = version["libraries"].as_array().unwrap();
+        let names: Vec<&str> = libraries.iter().map(|library| library["name"].as_str().unwrap()).collect();
+        assert_eq!(names, [FORGE, "net.minecraft:launchwrapper:1.12", "com.typesafe.akka:akka-actor_2.11:2.3.3", "org.ow2.asm:asm-all:5.0.3"]);
+        assert_eq!(libraries[0]["url"], "https://maven.minecraftforge.net/");
+        assert_eq!(libraries[1]["url"], MOJANG_LIBRARIES_URL);
+
+        let universal = game_dir.join("libraries").join(MavenCoordinate::parse(FORGE).unwrap().path());
+        assert_eq!(std::fs::read(universal).unwrap(), b"universal");
+
+        // the universal jar is copied, not fetched
+        let fetched: Vec<&str> = installer.libraries.iter().map(|(_, url, _)| url.as_str()).collect();
+        assert_eq!(fetched, [
+            "https://libraries.minecraft.net/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar",
+            "https://maven.minecraftforge.net/com/typesafe/akka/akka-actor_2.11/2.3.3/akka-actor_2.11-2.3.3.jar",
+            "https://libraries.minecraft.net/org/ow2/asm/asm-all/5.0.3/asm-all-5.0.3.jar",
+        ]);
+        assert_eq!(installer.libraries[1].2, ["ed62e9fc709ca0f2ff1a3220daa8b70a2870078e"]);
+    }
+
+    #[test]
+    fn needs_the_vanilla_version() {
+        let game_dir = scratch_dir("legacy-forge-no-vanilla");
+        write_zip(&game_dir.join("installer.jar"), &[(
+            "install_profile.json",
+            json!({
+                "install": { "target": VERSION_ID, "path": FORGE, "filePath": "forge-universal.jar", "minecraft": "1.7.10" },
+                "versionInfo": { "id": VERSION_ID, "libraries": [] },
+            }).to_string().as_bytes(),
+        )]);
+
+        let mut installer = LegacyForgeInstaller::open(game_dir.join("installer.jar")).unwrap();
+        let mut queue = Queue::new(1024, 1, None, Duration::from_millis(10));
+        assert!(installer.prepare(&game_dir, &mut queue).is_err());
+    }
+
+    #[test]
+    fn strips_the_signature_trailer() {
+        let mut data = b"pack200".to_vec();
+        data.extend_from_slice(b"checksums");
+        data.extend_from_slice(&9u32.to_le_bytes());
+        data.extend_from_slice(b"SIGN");
+        assert_eq!(pack200_data(&data).unwrap(), b"pack200");
+
+        let mut empty = 0u32.to_le_bytes().to_vec();
+        empty.extend_from_slice(b"SIGN");
+        assert_eq!(pack200_data(&empty).unwrap(), b"");
+
+        assert!(pack200_data(b"pack200").is_err());
+        assert!(pack200_data(b"SIGN").is_err());
+        let mut truncated = 100u32.to_le_bytes().to_vec();
+        truncated.extend_from_slice(b"SIGN");
+        assert!(pack200_data(&truncated).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use reqwest::Client;
+use serde_json::Value as Json;
+
+use crate::deserialize::Library;
+use crate::download::{Queue, Task};
+
+pub mod fabric;
+pub mod quilt;
+pub mod forge;
+pub mod legacy_forge;
+
+// Fetch a loader's version profile and write it to versions/<id>/<id>.json.
+// Returns the id and the libraries it lists
+pub async fn install_profile<P>(client: &Client, url: &str, versions_root: P) -> Result<(String, Vec<Library>)>
+where P: AsRef<Path>,
+{
+    let profile: Json = client.get(url).send().await?.error_for_status()?.json().await?;
+    let id = profile["id"]
+        .as_str()
+        .ok_or(anyhow!("Loader profile from {} has no id", url))?
+        .to_owned();
+
+    let dir = versions_root.as_ref().join(&id);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(format!("{}.json", id)), serde_json::to_string_pretty(&profile)?)?;
+
+    let libraries = profile["libraries"]
+        .as_array()
+        .map(|libraries| {
+            libraries.iter()
+                .filter_map(|library| serde_json::from_value::<Library>(library.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok((id, libraries))
+}
+
+// Download tasks for the libraries not yet under `library_root`
+pub fn library_tasks<P>(libraries: &[Library], library_root: P) -> Vec<Task>
+where P: AsRef<Path>,
+{
+    let library_root = library_root.as_ref();
+    libraries.iter()
+        .filter(|library| !library.download_item.url.is_empty())
+        .map(|library| (library, library_root.join(library.path())))
+        .filter(|(_, path)| !path.is_file())
+        .map(|(library, path)| Task::new(&library.download_item.url, path, library.download_item.size).with_sha1(&library.download_item.sha1))
+        .collect()
+}
+
+pub fn queue_libraries<P>(libraries: &[Library], library_root: P, queue: &mut Queue)
+where P: AsRef<Path>,
+{
+    for task in library_tasks(libraries, library_root) {
+        queue.push_task(task);
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::deserialize::Library;
+use super::install_profile;
+
+pub const QUILT_META_URL: &str = "https://meta.quiltmc.org";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LoaderVersion {
+    pub separator: String,
+    pub build: u32,
+    pub maven: String,
+    pub version: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct IntermediaryVersion {
+    pub maven: String,
+    pub version: String,
+}
+
+#[derive(Deserialize)]
+struct LoaderEntry {
+    loader: LoaderVersion,
+}
+
+pub struct QuiltMeta {
+    pub base_url: String,
+    client: Client,
+}
+
+impl QuiltMeta {
+    pub fn new(base_url: &str) -> QuiltMeta {
+        QuiltMeta {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client: Client::new(),
+        }
+    }
+
+    pub async fn loader_versions(&self, game_version: &str) -> Result<Vec<LoaderVersion>> {
+        let url = format!("{}/v3/versions/loader/{}", self.base_url, game_version);
+        let entries: Vec<LoaderEntry> = self.client.get(&url).send().await?.error_for_status()?.json().await?;
+
+        Ok(entries.into_iter().map(|entry| entry.loader).collect())
+    }
+
+    // Mappings Quilt puts between the game and the loader
+    pub async fn intermediary_versions(&self, game_version: &str) -> Result<Vec<IntermediaryVersion>> {
+        let url = format!("{}/v3/versions/intermediary/{}", self.base_url, game_version);
+        Ok(self.client.get(&url).send().await?.error_for_status()?.json().await?)
+    }
+
+    // Writes versions/<id>/<id>.json inheriting from `game_version`; the profile
+    // carries the loader together with its intermediary libraries
+    pub async fn install<P>(&self, game_version: &str, loader_version: &str, versions_root: P) -> Result<(String, Vec<Library>)>
+    where P: AsRef<Path>,
+    {
+        if self.intermediary_versions(game_version).await?.is_empty() {
+            bail!("Quilt has no intermediary mappings for {}", game_version);
+        }
+
+        let url = format!("{}/v3/versions/loader/{}/{}/profile/json", self.base_url, game_version, loader_version);
+        install_profile(&self.client, &url, versions_root).await
+    }
+}
+
+impl Default for QuiltMeta {
+    fn default() -> Self {
+        QuiltMeta::new(QUILT_META_URL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value as Json, json};
+    use warp::Filter;
+
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    // A meta server with one loader build, and intermediary mappings only for
+    // 1.20.1
+    fn serve() -> QuiltMeta {
+        let versions = warp::path!("v3" / "versions" / "loader" / String).map(|game_version: String| {
+            let entries = if game_version == "1.20.1" {
+                json!([{
+                    "loader": { "separator": ".", "build": 5, "maven": "org.quiltmc:quilt-loader:0.26.0", "version": "0.26.0" },
+                    "hashed": { "maven": "org.quiltmc:hashed:1.20.1", "version": "1.20.1" },
+                    "intermediary": { "maven": "net.fabricmc:intermediary:1.20.1", "version": "1.20.1" },
+                }])
+            } else {
+                json!([])
+            };
+            warp::reply::json(&entries)
+        });
+        let intermediary = warp::path!("v3" / "versions" / "intermediary" / String).map(|game_version: String| {
+            let versions = if game_version == "1.20.1" {
+                json!([{ "maven": "net.fabricmc:intermediary:1.20.1", "version": "1.20.1", "stable": true }])
+            } else {
+                json!([])
+            };
+            warp::reply::json(&versions)
+        });
+        let profile = warp::path!("v3" / "versions" / "loader" / String / String / "profile" / "json").map(|game_version: String, loader_version: String| {
+            let id = format!("quilt-loader-{}-{}", loader_version, game_version);
+            warp::reply::json(&json!({
+                "id": id,
+                "inheritsFrom": game_version,
+                "mainClass": "org.quiltmc.loader.impl.launch.knot.KnotClient",
+                "libraries": [
+                    { "name": format!("org.quiltmc:quilt-loader:{}", loader_version), "url": "https://maven.quiltmc.org/repository/release/" },
+                    { "name": format!("net.fabricmc:intermediary:{}", game_version), "url": "https://maven.fabricmc.net/" },
+                ],
+            }))
+        });
+
+        let (addr, server) = warp::serve(profile.or(intermediary).or(versions)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        QuiltMeta::new(&format!("http://{}/", addr))
+    }
+
+    #[tokio::test]
+    async fn lists_loader_versions() {
+        let meta = serve();
+
+        let versions = meta.loader_versions("1.20.1").await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].maven, "org.quiltmc:quilt-loader:0.26.0");
+        assert!(meta.loader_versions("1.0").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn installs_a_profile_inheriting_the_game() {
+        let meta = serve();
+        let versions_root = scratch_dir("quilt-install");
+
+        let (id, libraries) = meta.install("1.20.1", "0.26.0", &versions_root).await.unwrap();
+        assert_eq!(id, "quilt-loader-0.26.0-1.20.1");
+        let written: Json = serde_json::from_str(&std::fs::read_to_string(versions_root.join(&id).join(format!("{}.json", id))).unwrap()).unwrap();
+        assert_eq!(written["inheritsFrom"], "1.20.1");
+        assert_eq!(written["id"], id.as_str());
+
+        assert_eq!(libraries.len(), 2);
+        assert_eq!(libraries[1].download_item.url, "https://maven.fabricmc.net/net/fabricmc/intermediary/1.20.1/intermediary-1.20.1.jar");
+    }
+
+    #[tokio::test]
+    async fn needs_intermediary_mappings() {
+        let meta = serve();
+        let versions_root = scratch_dir("quilt-no-intermediary");
+
+        let error = meta.install("23w13a_or_b", "0.26.0", &versions_root).await.err().unwrap();
+        assert!(error.to_string().contains("no intermediary mappings"));
+        assert!(std::fs::read_dir(&versions_root).unwrap().next().is_none());
+    }
+}
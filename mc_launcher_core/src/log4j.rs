@@ -0,0 +1,233 @@
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct LogRecord {
+    pub logger: String,
+    pub level: String,
+    pub thread: String,
+    pub timestamp: i64, // milliseconds
+    pub message: String,
+    pub throwable: Option<String>,
+    pub raw: bool, // not a log4j event, `message` holds the line as printed
+}
+
+impl LogRecord {
+    pub fn raw(line: &str) -> LogRecord {
+        LogRecord {
+            message: line.to_owned(),
+            raw: true,
+            ..Default::default()
+        }
+    }
+}
+
+// Feeds on the game's stdout line by line. With `logging.client` applied the
+// game prints <log4j:Event> elements spanning several lines, anything else is
+// passed through as a raw record
+#[derive(Default)]
+pub struct Log4jParser {
+    buffer: Option<String>,
+    lines: usize,
+}
+
+impl Log4jParser {
+    const EVENT_START: &'static str = "<log4j:Event";
+    const EVENT_END: &'static str = "</log4j:Event>";
+    const MAX_LINES: usize = 4096;
+
+    pub fn new() -> Log4jParser {
+        Log4jParser {
+            buffer: None,
+            lines: 0,
+        }
+    }
+
+    pub fn feed(&mut self, line: &str) -> Option<LogRecord> {
+        match self.buffer.as_mut() {
+            Some(buffer) => {
+                buffer.push('\n');
+                buffer.push_str(line);
+                self.lines += 1;
+            },
+            None => {
+                if !line.trim_start().starts_with(Log4jParser::EVENT_START) {
+                    return Some(LogRecord::raw(line));
+                }
+                self.buffer = Some(line.to_owned());
+                self.lines = 1;
+            },
+        }
+
+        let buffer = self.buffer.as_ref().unwrap();
+        if buffer.contains(Log4jParser::EVENT_END) || is_self_closing(buffer) {
+            let event = self.buffer.take().unwrap();
+            return Some(parse_event(&event).unwrap_or_else(|| LogRecord::raw(&event)));
+        }
+        if self.lines >= Log4jParser::MAX_LINES {
+            return self.flush();
+        }
+        None
+    }
+
+    // Whatever is left once the stream closes
+    pub fn flush(&mut self) -> Option<LogRecord> {
+        self.buffer.take().map(|event| LogRecord::raw(&event))
+    }
+}
+
+fn is_self_closing(event: &str) -> bool {
+    event.find('>').is_some_and(|end| event[..end].ends_with('/'))
+}
+
+fn parse_event(event: &str) -> Option<LogRecord> {
+    let start = event.find(Log4jParser::EVENT_START)? + Log4jParser::EVENT_START.len();
+    let end = start + event[start..].find('>')?;
+    let attributes = parse_attributes(event[start..end].trim_end_matches('/'));
+
+    let attribute = |name: &str| {
+        attributes.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+
+    Some(LogRecord {
+        logger: attribute("logger"),
+        level: attribute("level"),
+        thread: attribute("thread"),
+        timestamp: attribute("timestamp").parse().unwrap_or_default(),
+        message: element_text(&event[end..], "log4j:Message").unwrap_or_default(),
+        throwable: element_text(&event[end..], "log4j:Throwable"),
+        raw: false,
+    })
+}
+
+fn parse_attributes(mut source: &str) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    loop {
+        source = source.trim_start();
+        let eq = match source.find('=') {
+            Some(eq) => eq,
+            None => break,
+        };
+        let name = source[..eq].trim().to_owned();
+        let rest = source[eq + 1..].trim_start();
+
+        let quote = match rest.chars().next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => break,
+        };
+        let close = match rest[1..].find(quote) {
+            Some(close) => close + 1,
+            None => break,
+        };
+        out.push((name, unescape(&rest[1..close])));
+        source = &rest[close + 1..];
+    }
+    out
+}
+
+fn element_text(source: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+
+    let start = source.find(&open)? + open.len();
+    let end = start + source[start..].find(&close)?;
+    let inner = &source[start..end];
+
+    // Concatenate CDATA sections, unescape text between them
+    let mut out = String::new();
+    let mut rest = inner;
+    while let Some(begin) = rest.find("<![CDATA[") {
+        out.push_str(&unescape(&rest[..begin]));
+        let body = &rest[begin + 9..];
+        match body.find("]]>") {
+            Some(end) => {
+                out.push_str(&body[..end]);
+                rest = &body[end + 3..];
+            },
+            None => {
+                out.push_str(body);
+                rest = "";
+            },
+        }
+    }
+    out.push_str(&unescape(rest));
+
+    Some(out.trim_end_matches(&['\r', '\n'][..]).to_owned())
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(parser: &mut Log4jParser, text: &str) -> Vec<LogRecord> {
+        text.lines().filter_map(|line| parser.feed(line)).collect()
+    }
+
+    #[test]
+    fn parses_a_multiline_event() {
+        let mut parser = Log4jParser::new();
+        let records = feed_all(&mut parser, r#"<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1700000000123" level="WARN" thread="Render thread">
+  <log4j:Message><![CDATA[Missing <texture> & model]]></log4j:Message>
+  <log4j:Throwable><![CDATA[java.io.IOException: gone
+	at Foo.bar(Foo.java:1)
+]]></log4j:Throwable>
+</log4j:Event>"#);
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert!(!record.raw);
+        assert_eq!(record.logger, "net.minecraft.client.Minecraft");
+        assert_eq!(record.level, "WARN");
+        assert_eq!(record.thread, "Render thread");
+        assert_eq!(record.timestamp, 1700000000123);
+        assert_eq!(record.message, "Missing <texture> & model");
+        assert_eq!(record.throwable.as_deref(), Some("java.io.IOException: gone\n\tat Foo.bar(Foo.java:1)"));
+    }
+
+    #[test]
+    fn unescapes_attributes_and_text() {
+        let mut parser = Log4jParser::new();
+        let record = parser.feed(r#"<log4j:Event logger='a&amp;b' level="INFO" thread="&quot;main&quot;" timestamp="1"><log4j:Message>1 &lt; 2 <![CDATA[&amp;]]></log4j:Message></log4j:Event>"#).unwrap();
+
+        assert_eq!(record.logger, "a&b");
+        assert_eq!(record.thread, "\"main\"");
+        assert_eq!(record.message, "1 < 2 &amp;");
+        assert_eq!(record.throwable, None);
+    }
+
+    #[test]
+    fn passes_other_lines_through() {
+        let mut parser = Log4jParser::new();
+        let records = feed_all(&mut parser, "[LWJGL] GLFW error\n<log4j:Event logger=\"x\" level=\"INFO\" thread=\"t\" timestamp=\"5\"/>\nplain");
+
+        assert_eq!(records.len(), 3);
+        assert!(records[0].raw);
+        assert_eq!(records[0].message, "[LWJGL] GLFW error");
+        assert!(!records[1].raw);
+        assert_eq!(records[1].timestamp, 5);
+        assert_eq!(records[1].message, "");
+        assert!(records[2].raw);
+    }
+
+    #[test]
+    fn flushes_an_unfinished_event_raw() {
+        let mut parser = Log4jParser::new();
+        assert!(parser.feed("<log4j:Event logger=\"x\" level=\"INFO\">").is_none());
+        assert!(parser.feed("  <log4j:Message><![CDATA[cut").is_none());
+
+        let record = parser.flush().unwrap();
+        assert!(record.raw);
+        assert!(record.message.ends_with("cut"));
+        assert!(parser.flush().is_none());
+    }
+}
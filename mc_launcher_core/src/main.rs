@@ -1,11 +1,7 @@
-use futures::TryFutureExt;
 use mc_launcher_core::account::AccountInfo;
 use mc_launcher_core::config::LauncherConfig;
-use mc_launcher_core::instance::Instance;
 
-use anyhow::{Result, anyhow};
-use serde_json::value::Value as Json;
-use tokio::sync::mpsc;
+use anyhow::Result;
 
 fn main() {
     tokio_uring::start(run()).unwrap()
@@ -24,8 +20,10 @@ async fn test_login(info: &mut AccountInfo) -> Result<()> {
     info.oauth2_login().await
 }
 
+// manual checks, call them from `run` when needed
+#[allow(dead_code)]
 async fn test_save(info: &mut AccountInfo) -> Result<()> {
-    print_profile(&info);
+    print_profile(info);
     
     let mut config = LauncherConfig::default();
     config.accounts.push(info.clone());
@@ -39,11 +37,13 @@ async fn test_save(info: &mut AccountInfo) -> Result<()> {
     }
 }
 
+#[allow(dead_code)]
 async fn test_load() -> Result<AccountInfo> {
     let config = LauncherConfig::load("./.RMCL.config.json").await?;
     Ok(config.accounts[0].clone())
 }
 
+#[allow(dead_code)]
 fn print_profile(info: &AccountInfo) {
     println!("{}", serde_json::to_string(info).unwrap());
 }
@@ -0,0 +1,346 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::download::{Queue, Task};
+use crate::instance::mods::{DISABLED_SUFFIX, ModLoader};
+use super::{FileHash, PackFiles, PackVersion, extract_prefix, safe_join};
+
+pub const CURSEFORGE_API_URL: &str = "https://api.curseforge.com";
+// where files the API won't hand out a download link for still live
+const FORGECDN_URL: &str = "https://edge.forgecdn.net/files";
+const HASH_SHA1: u32 = 1;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub minecraft: ManifestMinecraft,
+    pub manifest_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub files: Vec<ManifestFile>,
+    #[serde(default = "default_overrides")]
+    pub overrides: String,
+}
+
+fn default_overrides() -> String {
+    "overrides".to_owned()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestMinecraft {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<ManifestLoader>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ManifestLoader {
+    pub id: String, // forge-47.1.0, fabric-0.14.21
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u32,
+    #[serde(rename = "fileID")]
+    pub file_id: u32,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseFile {
+    pub id: u32,
+    pub mod_id: u32,
+    pub file_name: String,
+    pub download_url: Option<String>,
+    pub file_length: u64,
+    #[serde(default)]
+    pub hashes: Vec<CurseHash>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CurseHash {
+    pub value: String,
+    pub algo: u32,
+}
+
+#[derive(Deserialize)]
+struct DataResponse<T> {
+    data: T,
+}
+
+impl CurseFile {
+    pub fn url(&self) -> String {
+        match &self.download_url {
+            Some(url) if !url.is_empty() => url.clone(),
+            _ => format!("{}/{}/{}/{}", FORGECDN_URL, self.id / 1000, self.id % 1000, self.file_name),
+        }
+    }
+
+    pub fn sha1(&self) -> Option<&str> {
+        self.hashes.iter().find(|hash| hash.algo == HASH_SHA1).map(|hash| hash.value.as_str())
+    }
+}
+
+pub struct CurseForge {
+    pub base_url: String,
+    api_key: Option<String>,
+    client: Client,
+}
+
+impl CurseForge {
+    pub fn new(base_url: &str, api_key: Option<&str>) -> CurseForge {
+        CurseForge {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            api_key: api_key.map(|key| key.to_owned()),
+            client: Client::new(),
+        }
+    }
+
+    pub async fn files(&self, file_ids: &[u32]) -> Result<Vec<CurseFile>> {
+        let url = format!("{}/v1/mods/files", self.base_url);
+        let mut request = self.client.post(&url).json(&json!({ "fileIds": file_ids }));
+        if let Some(key) = &self.api_key {
+            request = request.header("x-api-key", key);
+        }
+        let response: DataResponse<Vec<CurseFile>> = request.send().await?.error_for_status()?.json().await?;
+        Ok(response.data)
+    }
+}
+
+impl Default for CurseForge {
+    fn default() -> Self {
+        CurseForge::new(CURSEFORGE_API_URL, None)
+    }
+}
+
+pub struct CurseForgePack {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl CurseForgePack {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<CurseForgePack> {
+        let path = path.as_ref().to_path_buf();
+        let mut archive = zip::ZipArchive::new(File::open(&path)?)?;
+
+        let mut text = String::new();
+        archive.by_name("manifest.json")?.read_to_string(&mut text)?;
+        let manifest: Manifest = serde_json::from_str(&text)?;
+        if manifest.manifest_type != "minecraftModpack" {
+            bail!("{} isn't a modpack manifest", path.display());
+        }
+        Ok(CurseForgePack { path, manifest })
+    }
+
+    pub fn version(&self) -> Result<PackVersion> {
+        let loaders = &self.manifest.minecraft.mod_loaders;
+        let loader = match loaders.iter().find(|loader| loader.primary).or_else(|| loaders.first()) {
+            Some(loader) => {
+                let (name, version) = loader.id.split_once('-').ok_or(anyhow!("Unknown mod loader {}", loader.id))?;
+                let loader = match name {
+                    "forge" => ModLoader::Forge,
+                    "neoforge" => ModLoader::NeoForge,
+                    "fabric" => ModLoader::Fabric,
+                    "quilt" => ModLoader::Quilt,
+                    _ => bail!("Unknown mod loader {}", loader.id),
+                };
+                Some((loader, version.to_owned()))
+            },
+            None => None,
+        };
+        Ok(PackVersion { minecraft: self.manifest.minecraft.version.clone(), loader })
+    }
+
+    // Resolve every file through the API, queue them into mods/ and copy the
+    // overrides. Optional files are installed disabled, to be turned on from the
+    // mod list. Verify the returned files after the queue
+    pub async fn prepare<P: AsRef<Path>>(&self, api: &CurseForge, game_dir: P, queue: &mut Queue) -> Result<PackFiles> {
+        let game_dir = game_dir.as_ref();
+        let mods_dir = game_dir.join("mods");
+        std::fs::create_dir_all(&mods_dir)?;
+
+        let file_ids: Vec<u32> = self.manifest.files.iter().map(|file| file.file_id).collect();
+        let resolved = if file_ids.is_empty() { Vec::new() } else { api.files(&file_ids).await? };
+
+        let mut files = Vec::new();
+        for declared in &self.manifest.files {
+            let file = resolved.iter()
+                .find(|file| file.id == declared.file_id)
+                .ok_or(anyhow!("File {} of project {} in {} can't be found", declared.file_id, declared.project_id, self.manifest.name))?;
+            let file_name = if declared.required { file.file_name.clone() } else { format!("{}{}", file.file_name, DISABLED_SUFFIX) };
+            let path = safe_join(&mods_dir, &file_name)?;
+            // the length is all there is to check files without a sha1 against
+            let hash = match file.sha1() {
+                Some(sha1) => FileHash::Sha1(sha1.to_owned()),
+                None => FileHash::Size(file.file_length),
+            };
+
+            if !hash.matches(&path) {
+                let task = Task::new(&file.url(), &path, file.file_length);
+                queue.push_task(match &hash {
+                    FileHash::Sha1(sha1) => task.with_sha1(sha1),
+                    _ => task,
+                });
+            }
+            files.push((path, hash));
+        }
+
+        let mut archive = zip::ZipArchive::new(File::open(&self.path)?)?;
+        extract_prefix(&mut archive, &self.manifest.overrides, game_dir)?;
+
+        Ok(PackFiles { files })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use serde_json::{Value as Json, json};
+    use tokio::time::Duration;
+    use warp::Filter;
+
+    use super::*;
+    use crate::download::wait;
+    use crate::test_util::{scratch_dir, write_zip};
+
+    const CONTENTS: &[(&str, &[u8])] = &[("jei.jar", b"jei"), ("patchouli.jar", b"patchouli"), ("extras.jar", b"extras")];
+
+    // Files 1 to 3 of the contents above. Only jei has a sha1, and only
+    // requests carrying the key get an answer
+    fn serve() -> CurseForge {
+        let port = Arc::new(AtomicU16::new(0));
+        let file_port = port.clone();
+        let files = warp::path!("v1" / "mods" / "files")
+            .and(warp::post())
+            .and(warp::header::exact("x-api-key", "secret"))
+            .and(warp::body::json())
+            .map(move |body: Json| {
+                let port = file_port.load(Ordering::SeqCst);
+                let data: Vec<Json> = body["fileIds"].as_array().unwrap().iter()
+                    .filter_map(|id| id.as_u64())
+                    .filter(|id| (1..=3).contains(id))
+                    .map(|id| {
+                        let (name, data) = CONTENTS[id as usize - 1];
+                        let hashes = if id == 1 { json!([{ "value": sha1::Sha1::from(data).digest().to_string(), "algo": 1 }]) } else { json!([]) };
+                        json!({
+                            "id": id, "modId": 100 + id, "fileName": name, "fileLength": data.len(),
+                            "downloadUrl": format!("http://127.0.0.1:{}/download/{}", port, name), "hashes": hashes,
+                        })
+                    })
+                    .collect();
+                warp::reply::json(&json!({ "data": data }))
+            });
+        let download = warp::path!("download" / String).map(|name: String| {
+            CONTENTS.iter().find(|(file, _)| *file == name).unwrap().1.to_vec()
+        });
+
+        let (addr, server) = warp::serve(files.or(download)).bind_ephemeral(([127, 0, 0, 1], 0));
+        port.store(addr.port(), Ordering::SeqCst);
+        tokio::spawn(server);
+        CurseForge::new(&format!("http://{}", addr), Some("secret"))
+    }
+
+    fn pack(dir: &Path, loaders: Json, files: Json) -> CurseForgePack {
+        let manifest = json!({
+            "minecraft": { "version": "1.20.1", "modLoaders": loaders },
+            "manifestType": "minecraftModpack",
+            "name": "Test Pack",
+            "files": files,
+        });
+        let path = dir.join("pack.zip");
+        write_zip(&path, &[
+            ("manifest.json", manifest.to_string().as_bytes()),
+            ("overrides/config/jei.toml", b"a = 1"),
+            ("overrides/options.txt", b"fov:90"),
+        ]);
+        CurseForgePack::open(&path).unwrap()
+    }
+
+    #[test]
+    fn maps_mod_loaders() {
+        let dir = scratch_dir("curseforge-loaders");
+        let version = pack(&dir, json!([{ "id": "fabric-0.14.21" }, { "id": "forge-47.1.0", "primary": true }]), json!([])).version().unwrap();
+        assert_eq!(version.minecraft, "1.20.1");
+        assert_eq!(version.loader, Some((ModLoader::Forge, "47.1.0".to_owned())));
+
+        let version = pack(&dir, json!([{ "id": "neoforge-20.4.80" }]), json!([])).version().unwrap();
+        assert_eq!(version.loader, Some((ModLoader::NeoForge, "20.4.80".to_owned())));
+        assert_eq!(pack(&dir, json!([]), json!([])).version().unwrap().loader, None);
+        assert!(pack(&dir, json!([{ "id": "rift-1.0" }]), json!([])).version().is_err());
+    }
+
+    #[test]
+    fn falls_back_to_forgecdn() {
+        let file: CurseFile = serde_json::from_value(json!({ "id": 4567890, "modId": 1, "fileName": "jei.jar", "fileLength": 3, "downloadUrl": null })).unwrap();
+        assert_eq!(file.url(), "https://edge.forgecdn.net/files/4567/890/jei.jar");
+        assert_eq!(file.sha1(), None);
+    }
+
+    #[tokio::test]
+    async fn installs_files_and_overrides() {
+        let api = serve();
+        let dir = scratch_dir("curseforge-install");
+        let pack = pack(&dir, json!([]), json!([
+            { "projectID": 101, "fileID": 1, "required": true },
+            { "projectID": 102, "fileID": 2 },
+            { "projectID": 103, "fileID": 3, "required": false },
+        ]));
+        let game_dir = dir.join("game");
+
+        let mut queue = Queue::new(1024, 4, None, Duration::from_millis(10));
+        let files = pack.prepare(&api, &game_dir, &mut queue).await.unwrap();
+        let queue = queue.run_in_background();
+        wait(&queue).await;
+        assert_eq!(queue.lock().failed(), 0);
+        files.verify().unwrap();
+
+        let mods = game_dir.join("mods");
+        assert_eq!(std::fs::read(mods.join("jei.jar")).unwrap(), b"jei");
+        assert_eq!(std::fs::read(mods.join("patchouli.jar")).unwrap(), b"patchouli");
+        assert_eq!(std::fs::read(mods.join("extras.jar.disabled")).unwrap(), b"extras");
+        assert!(matches!(files.files[1].1, FileHash::Size(9)));
+
+        assert_eq!(std::fs::read(game_dir.join("config/jei.toml")).unwrap(), b"a = 1");
+        assert_eq!(std::fs::read(game_dir.join("options.txt")).unwrap(), b"fov:90");
+        assert!(!game_dir.join("overrides").exists());
+
+        // a file changed since then no longer passes
+        std::fs::write(mods.join("patchouli.jar"), b"patch").unwrap();
+        assert!(files.verify().is_err());
+    }
+
+    #[tokio::test]
+    async fn fails_on_an_unknown_file() {
+        let api = serve();
+        let dir = scratch_dir("curseforge-unknown");
+        let pack = pack(&dir, json!([]), json!([{ "projectID": 101, "fileID": 1 }, { "projectID": 109, "fileID": 9 }]));
+
+        let mut queue = Queue::new(1024, 4, None, Duration::from_millis(10));
+        let error = pack.prepare(&api, dir.join("game"), &mut queue).await.err().unwrap();
+        assert!(error.to_string().contains("File 9 of project 109"));
+
+        let keyless = CurseForge::new(&api.base_url, None);
+        assert!(keyless.files(&[1]).await.is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::io::{Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::instance::mods::ModLoader;
+use crate::util::{file_sha1, file_sha512};
+
+pub mod mrpack;
+pub mod curseforge;
+pub mod multimc;
+pub mod official;
+
+// The game and loader a pack is built for
+#[derive(Clone, Serialize, Debug)]
+pub struct PackVersion {
+    pub minecraft: String,
+    pub loader: Option<(ModLoader, String)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum FileHash {
+    Sha1(String),
+    Sha512(String),
+    Size(u64), // all some sources give
+}
+
+impl FileHash {
+    pub fn matches(&self, path: &Path) -> bool {
+        match self {
+            FileHash::Sha1(sha1) => file_sha1(path).is_ok_and(|actual| &actual == sha1),
+            FileHash::Sha512(sha512) => file_sha512(path).is_ok_and(|actual| &actual == sha512),
+            FileHash::Size(size) => std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.len() == *size),
+        }
+    }
+}
+
+// Files queued from a pack, checked once the queue is done
+pub struct PackFiles {
+    pub files: Vec<(PathBuf, FileHash)>,
+}
+
+impl PackFiles {
+    pub fn verify(&self) -> Result<()> {
+        let mut corrupt = Vec::new();
+        for (path, hash) in &self.files {
+            if !hash.matches(path) {
+                corrupt.push(path.display().to_string());
+            }
+        }
+        if !corrupt.is_empty() {
+            bail!("{} pack files failed verification: {}", corrupt.len(), corrupt.join(", "));
+        }
+        Ok(())
+    }
+}
+
+// A pack's own profile, inheriting from the loader (or vanilla) version, so it
+// shows up as an instance of its own. Returns the id used
+pub fn create_instance<P: AsRef<Path>>(versions_root: P, name: &str, parent_id: &str) -> Result<String> {
+    let id: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' || c == ' ' { c } else { '_' })
+        .collect::<String>()
+        .trim()
+        .to_owned();
+    if id.is_empty() || id.starts_with('.') {
+        bail!("Invalid instance name {}", name);
+    }
+
+    let dir = versions_root.as_ref().join(&id);
+    if dir.exists() {
+        bail!("Version {} already exists", id);
+    }
+    std::fs::create_dir_all(&dir)?;
+    let profile = json!({
+        "id": id,
+        "inheritsFrom": parent_id,
+        "type": "modpack",
+    });
+    std::fs::write(dir.join(format!("{}.json", id)), serde_json::to_string_pretty(&profile)?)?;
+    Ok(id)
+}
+
+// A relative path from a pack index or manifest, refusing anything that would
+// leave `root`
+pub fn safe_join(root: &Path, relative: &str) -> Result<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.components().any(|component| !matches!(component, Component::Normal(_))) {
+        bail!("Unsafe path {} under {}", relative.display(), root.display());
+    }
+    Ok(root.join(relative))
+}
+
+// Extract every entry under `prefix` into `target`, with the prefix removed.
+// Returns how many files were written
+pub fn extract_prefix<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, prefix: &str, target: &Path) -> Result<usize> {
+    let mut count = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let relative = match entry.enclosed_name().and_then(|name| name.strip_prefix(prefix).ok()) {
+            Some(relative) if relative.components().next().is_some() => relative.to_path_buf(),
+            _ => continue,
+        };
+        let path = target.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut entry, &mut std::fs::File::create(&path)?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+pub enum ImportMode {
+    Copy,
+    Link, // hard links, so both launchers see the same files
+}
+
+// Bring the tree under `src` into `dst`, leaving files already in `dst` alone.
+// `skip` names top level entries to leave out. Links fall back to copies across
+// filesystems
+pub fn import_tree(src: &Path, dst: &Path, mode: ImportMode, skip: &[&str]) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if skip.iter().any(|skipped| name.as_os_str() == *skipped) {
+            continue;
+        }
+        let target = dst.join(&name);
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            import_tree(&entry.path(), &target, mode, &[])?;
+        }
+        else if file_type.is_file() && !target.exists() {
+            let linked = mode == ImportMode::Link && std::fs::hard_link(entry.path(), &target).is_ok();
+            if !linked {
+                std::fs::copy(entry.path(), &target)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use zip::write::FileOptions;
+
+use crate::download::{Queue, Task};
+use crate::instance::mods::ModLoader;
+use crate::modrinth::{FileHashes, Modrinth};
+use crate::util::{file_sha1, file_sha512};
+use super::{FileHash, PackFiles, PackVersion, extract_prefix, safe_join};
+
+pub const INDEX_FILE: &str = "modrinth.index.json";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String, // the pack's own version
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub files: Vec<MrpackFile>,
+    pub dependencies: HashMap<String, String>, // minecraft, forge, neoforge, fabric-loader, quilt-loader
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackFile {
+    pub path: String,
+    pub hashes: FileHashes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<FileEnv>,
+    pub downloads: Vec<String>,
+    pub file_size: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FileEnv {
+    pub client: String, // required, optional or unsupported
+    pub server: String,
+}
+
+const LOADER_KEYS: &[(&str, ModLoader)] = &[
+    ("fabric-loader", ModLoader::Fabric),
+    ("quilt-loader", ModLoader::Quilt),
+    ("forge", ModLoader::Forge),
+    ("neoforge", ModLoader::NeoForge),
+];
+
+pub struct Mrpack {
+    pub path: PathBuf,
+    pub index: MrpackIndex,
+}
+
+impl Mrpack {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Mrpack> {
+        let path = path.as_ref().to_path_buf();
+        let mut archive = zip::ZipArchive::new(File::open(&path)?)?;
+
+        let mut text = String::new();
+        archive.by_name(INDEX_FILE)?.read_to_string(&mut text)?;
+        let index: MrpackIndex = serde_json::from_str(&text)?;
+        if index.game != "minecraft" {
+            bail!("{} is a pack for {}", path.display(), index.game);
+        }
+        Ok(Mrpack { path, index })
+    }
+
+    pub fn version(&self) -> Result<PackVersion> {
+        let minecraft = self.index.dependencies
+            .get("minecraft")
+            .ok_or(anyhow!("{} doesn't name a Minecraft version", self.index.name))?
+            .clone();
+        let loader = LOADER_KEYS.iter()
+            .find_map(|(key, loader)| self.index.dependencies.get(*key).map(|version| (*loader, version.clone())));
+        Ok(PackVersion { minecraft, loader })
+    }
+
+    // Queue every file the client needs into `game_dir` and copy the overrides,
+    // client-overrides last so they win. Verify the returned files after the queue
+    pub fn prepare<P: AsRef<Path>>(&self, game_dir: P, queue: &mut Queue) -> Result<PackFiles> {
+        let game_dir = game_dir.as_ref();
+        std::fs::create_dir_all(game_dir)?;
+
+        let mut files = Vec::new();
+        for file in &self.index.files {
+            if file.env.as_ref().is_some_and(|env| env.client == "unsupported") {
+                continue;
+            }
+            let path = safe_join(game_dir, &file.path)?;
+            let url = file.downloads.first().ok_or(anyhow!("{} has no download", file.path))?;
+
+            let intact = path.is_file() && file_sha512(&path).is_ok_and(|sha512| sha512 == file.hashes.sha512);
+            if !intact {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                queue.push_task(Task::new(url, &path, file.file_size).with_sha1(&file.hashes.sha1));
+            }
+            files.push((path, FileHash::Sha512(file.hashes.sha512.clone())));
+        }
+
+        let mut archive = zip::ZipArchive::new(File::open(&self.path)?)?;
+        extract_prefix(&mut archive, "overrides", game_dir)?;
+        extract_prefix(&mut archive, "client-overrides", game_dir)?;
+
+        Ok(PackFiles { files })
+    }
+}
+
+// Write `game_dir`'s mods and config as a .mrpack. Mods Modrinth knows are
+// referenced by hash, the rest go into overrides/ with the config
+pub async fn export<P, Q>(modrinth: &Modrinth, game_dir: P, name: &str, pack_version: &str, version: &PackVersion, out: Q) -> Result<()>
+where
+        P: AsRef<Path>,
+        Q: AsRef<Path>, {
+    let game_dir = game_dir.as_ref();
+
+    let mut jars = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(game_dir.join("mods")) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_file() && path.extension().is_some_and(|extension| extension == "jar") {
+                jars.push((file_sha512(&path)?, path));
+            }
+        }
+    }
+    jars.sort_by(|a, b| a.1.cmp(&b.1));
+    let known = if jars.is_empty() {
+        HashMap::new()
+    } else {
+        modrinth.versions_by_hash(&jars.iter().map(|(sha512, _)| sha512.clone()).collect::<Vec<String>>()).await?
+    };
+
+    let mut files = Vec::new();
+    let mut overrides: Vec<PathBuf> = Vec::new();
+    for (sha512, path) in jars {
+        let hosted = known.get(&sha512)
+            .and_then(|version| version.files.iter().find(|file| file.hashes.sha512 == sha512));
+        match hosted {
+            Some(hosted) => files.push(MrpackFile {
+                path: format!("mods/{}", path.file_name().unwrap_or_default().to_string_lossy()),
+                hashes: FileHashes { sha1: file_sha1(&path)?, sha512 },
+                env: None,
+                downloads: vec![hosted.url.clone()],
+                file_size: std::fs::metadata(&path)?.len(),
+            }),
+            None => overrides.push(path),
+        }
+    }
+    collect_files(&game_dir.join("config"), &mut overrides)?;
+
+    let mut dependencies = HashMap::new();
+    dependencies.insert("minecraft".to_owned(), version.minecraft.clone());
+    if let Some((loader, loader_version)) = &version.loader {
+        let key = LOADER_KEYS.iter()
+            .find(|(_, known)| known == loader)
+            .map(|(key, _)| *key)
+            .unwrap_or("forge"); // legacy Forge
+        dependencies.insert(key.to_owned(), loader_version.clone());
+    }
+    let index = MrpackIndex {
+        format_version: 1,
+        game: "minecraft".to_owned(),
+        version_id: pack_version.to_owned(),
+        name: name.to_owned(),
+        summary: None,
+        files,
+        dependencies,
+    };
+
+    let mut writer = zip::ZipWriter::new(File::create(out.as_ref())?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    writer.start_file(INDEX_FILE, options)?;
+    writer.write_all(serde_json::to_string_pretty(&index)?.as_bytes())?;
+    for path in overrides {
+        let relative = path.strip_prefix(game_dir)?;
+        let name = Path::new("overrides").join(relative);
+        // zip paths always use forward slashes
+        let name = name.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        writer.start_file(name, options)?;
+        std::io::copy(&mut File::open(&path)?, &mut writer)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::instance::mods::ModLoader;
+use super::PackVersion;
+
+#[derive(Deserialize)]
+struct MmcPack {
+    components: Vec<Component>,
+}
+
+#[derive(Deserialize)]
+struct Component {
+    uid: String,
+    version: Option<String>,
+    #[serde(rename = "cachedVersion")]
+    cached_version: Option<String>,
+}
+
+// A MultiMC or Prism Launcher instance folder
+#[derive(Clone, Serialize, Debug)]
+pub struct MultiMcInstance {
+    pub dir: PathBuf,
+    pub name: String,
+    pub version: PackVersion,
+    // Only reported, the vanilla profile pins the LWJGL it runs with
+    pub lwjgl: Option<String>,
+    pub game_dir: Option<PathBuf>, // .minecraft, or minecraft in newer Prism
+}
+
+impl MultiMcInstance {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<MultiMcInstance> {
+        let dir = dir.as_ref().to_path_buf();
+
+        let config = read_cfg(&dir.join("instance.cfg"))?;
+        let pack: MmcPack = serde_json::from_str(&std::fs::read_to_string(dir.join("mmc-pack.json"))?)?;
+
+        let mut minecraft = None;
+        let mut lwjgl = None;
+        let mut loader = None;
+        for component in pack.components {
+            let version = match component.version.or(component.cached_version) {
+                Some(version) => version,
+                None => continue,
+            };
+            match component.uid.as_str() {
+                "net.minecraft" => minecraft = Some(version),
+                "org.lwjgl" | "org.lwjgl3" => lwjgl = Some(version),
+                "net.fabricmc.fabric-loader" => loader = Some((ModLoader::Fabric, version)),
+                "org.quiltmc.quilt-loader" => loader = Some((ModLoader::Quilt, version)),
+                "net.minecraftforge" => loader = Some((ModLoader::Forge, version)),
+                "net.neoforged" => loader = Some((ModLoader::NeoForge, version)),
+                _ => {},
+            }
+        }
+        let minecraft = minecraft.ok_or(anyhow!("{} has no Minecraft component", dir.display()))?;
+
+        let name = config.get("name")
+            .cloned()
+            .or_else(|| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+        let game_dir = [".minecraft", "minecraft"].iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_dir());
+
+        Ok(MultiMcInstance {
+            dir,
+            name,
+            version: PackVersion { minecraft, loader },
+            lwjgl,
+            game_dir,
+        })
+    }
+}
+
+// key=value lines; Prism puts them under [General]
+fn read_cfg(path: &Path) -> Result<HashMap<String, String>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text.lines()
+        .filter(|line| !line.starts_with('[') && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    #[test]
+    fn reads_prism_config_sections() {
+        let dir = scratch_dir("multimc-cfg");
+        std::fs::write(dir.join("instance.cfg"), "[General]\n# comment\nname = Skyblock\nInstanceType=OneSix\niconKey=default\nnotes=a=b\n").unwrap();
+
+        let config = read_cfg(&dir.join("instance.cfg")).unwrap();
+        assert_eq!(config.len(), 4);
+        assert_eq!(config["name"], "Skyblock");
+        assert_eq!(config["notes"], "a=b");
+    }
+
+    #[test]
+    fn opens_an_instance() {
+        let dir = scratch_dir("multimc-open").join("Skyblock");
+        std::fs::create_dir_all(dir.join("minecraft")).unwrap();
+        std::fs::write(dir.join("instance.cfg"), "InstanceType=OneSix\nname=Skyblock 1.20\n").unwrap();
+        std::fs::write(dir.join("mmc-pack.json"), r#"{
+            "formatVersion": 1,
+            "components": [
+                { "uid": "org.lwjgl3", "cachedVersion": "3.3.1", "dependencyOnly": true },
+                { "uid": "net.minecraft", "version": "1.20.1", "important": true },
+                { "uid": "net.fabricmc.intermediary", "version": "1.20.1" },
+                { "uid": "net.fabricmc.fabric-loader", "version": "0.15.11" },
+                { "uid": "com.example.unversioned" }
+            ]
+        }"#).unwrap();
+
+        let instance = MultiMcInstance::open(&dir).unwrap();
+        assert_eq!(instance.name, "Skyblock 1.20");
+        assert_eq!(instance.version.minecraft, "1.20.1");
+        assert_eq!(instance.version.loader, Some((ModLoader::Fabric, "0.15.11".to_owned())));
+        assert_eq!(instance.lwjgl.as_deref(), Some("3.3.1"));
+        assert_eq!(instance.game_dir, Some(dir.join("minecraft")));
+    }
+
+    #[test]
+    fn falls_back_to_the_folder_name() {
+        let dir = scratch_dir("multimc-name").join("Vanilla");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("instance.cfg"), "InstanceType=OneSix\n").unwrap();
+        std::fs::write(dir.join("mmc-pack.json"), r#"{ "components": [{ "uid": "net.minecraft", "version": "1.8.9" }] }"#).unwrap();
+
+        let instance = MultiMcInstance::open(&dir).unwrap();
+        assert_eq!(instance.name, "Vanilla");
+        assert_eq!(instance.version.loader, None);
+        assert_eq!(instance.game_dir, None);
+    }
+
+    #[test]
+    fn needs_a_minecraft_component() {
+        let dir = scratch_dir("multimc-empty");
+        std::fs::write(dir.join("instance.cfg"), "name=Broken\n").unwrap();
+        std::fs::write(dir.join("mmc-pack.json"), r#"{ "components": [{ "uid": "org.lwjgl3", "version": "3.3.1" }] }"#).unwrap();
+
+        assert!(MultiMcInstance::open(&dir).unwrap_err().to_string().ends_with("has no Minecraft component"));
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+
+use super::{ImportMode, import_tree};
+
+// Left out when a profile's game directory is imported, they are shared or the
+// official launcher's own
+pub const SKIPPED_ENTRIES: &[&str] = &[
+    "versions", "libraries", "assets", "runtime", "logs", "webcache", "webcache2",
+    "launcher_profiles.json", "launcher_accounts.json", "launcher_settings.json", "launcher_log.txt",
+];
+
+#[derive(Deserialize)]
+struct LauncherProfiles {
+    profiles: HashMap<String, RawProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawProfile {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    profile_type: Option<String>,
+    last_version_id: Option<String>,
+    game_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct LauncherProfile {
+    pub key: String,
+    pub name: String,
+    pub version_id: String,
+    pub game_dir: Option<PathBuf>, // None for the .minecraft folder itself
+}
+
+pub fn default_minecraft_dir() -> Option<PathBuf> {
+    if cfg!(target_os="windows") {
+        std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join(".minecraft"))
+    } else if cfg!(target_os="macos") {
+        std::env::var_os("HOME").map(|dir| PathBuf::from(dir).join("Library").join("Application Support").join("minecraft"))
+    } else {
+        std::env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".minecraft"))
+    }
+}
+
+// Profiles from launcher_profiles.json whose version is installed. The
+// latest-release and latest-snapshot ones resolve to the newest installed
+pub fn read_profiles<P: AsRef<Path>>(minecraft_dir: P) -> Result<Vec<LauncherProfile>> {
+    let minecraft_dir = minecraft_dir.as_ref();
+    let text = std::fs::read_to_string(minecraft_dir.join("launcher_profiles.json"))?;
+    let profiles: LauncherProfiles = serde_json::from_str(&text)?;
+    let versions_root = minecraft_dir.join("versions");
+
+    let mut out = Vec::new();
+    for (key, profile) in profiles.profiles {
+        let version_id = match (profile.last_version_id.as_deref(), profile.profile_type.as_deref()) {
+            (Some("latest-release"), _) | (None, Some("latest-release")) => newest_installed(&versions_root, "release"),
+            (Some("latest-snapshot"), _) | (None, Some("latest-snapshot")) => newest_installed(&versions_root, "snapshot"),
+            (Some(id), _) => Some(id.to_owned()),
+            (None, _) => None,
+        };
+        let version_id = match version_id {
+            Some(id) if versions_root.join(&id).join(format!("{}.json", id)).is_file() => id,
+            _ => continue,
+        };
+
+        out.push(LauncherProfile {
+            name: profile.name.filter(|name| !name.is_empty()).unwrap_or_else(|| version_id.clone()),
+            version_id,
+            game_dir: profile.game_dir.filter(|dir| dir.as_path() != minecraft_dir),
+            key,
+        });
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(out)
+}
+
+fn newest_installed(versions_root: &Path, version_type: &str) -> Option<String> {
+    std::fs::read_dir(versions_root).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().into_owned();
+            let text = std::fs::read_to_string(entry.path().join(format!("{}.json", id))).ok()?;
+            let json: Json = serde_json::from_str(&text).ok()?;
+            if json["type"].as_str()? != version_type {
+                return None;
+            }
+            Some((json["releaseTime"].as_str()?.to_owned(), id))
+        })
+        .max()
+        .map(|(_, id)| id)
+}
+
+// Link `version_id` and everything it inherits from into `game_dir`'s versions/,
+// along with the libraries and assets, reusing the files instead of downloading
+pub fn import_version<P, Q>(minecraft_dir: P, version_id: &str, game_dir: Q) -> Result<()>
+where
+        P: AsRef<Path>,
+        Q: AsRef<Path>, {
+    let (minecraft_dir, game_dir) = (minecraft_dir.as_ref(), game_dir.as_ref());
+
+    let mut chain = Vec::new();
+    let mut id = Some(version_id.to_owned());
+    while let Some(current) = id {
+        if chain.contains(&current) {
+            bail!("Version {} inherits from itself", current);
+        }
+        let dir = minecraft_dir.join("versions").join(&current);
+        let text = std::fs::read_to_string(dir.join(format!("{}.json", current)))
+            .map_err(|_| anyhow!("Version {} isn't installed in {}", current, minecraft_dir.display()))?;
+        let json: Json = serde_json::from_str(&text)?;
+
+        import_tree(&dir, &game_dir.join("versions").join(&current), ImportMode::Link, &[])?;
+        id = json["inheritsFrom"].as_str().map(|parent| parent.to_owned());
+        chain.push(current);
+    }
+
+    for shared in &["libraries", "assets"] {
+        let src = minecraft_dir.join(shared);
+        if src.is_dir() {
+            import_tree(&src, &game_dir.join(shared), ImportMode::Link, &[])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    fn install(minecraft_dir: &Path, profile: Json) {
+        let id = profile["id"].as_str().unwrap();
+        let dir = minecraft_dir.join("versions").join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("{}.json", id)), profile.to_string()).unwrap();
+    }
+
+    #[test]
+    fn resolves_latest_profiles() {
+        let dir = scratch_dir("official-profiles");
+        install(&dir, json!({ "id": "1.20.1", "type": "release", "releaseTime": "2023-06-12T13:25:51+00:00" }));
+        install(&dir, json!({ "id": "1.19.4", "type": "release", "releaseTime": "2023-03-14T12:56:18+00:00" }));
+        install(&dir, json!({ "id": "23w31a", "type": "snapshot", "releaseTime": "2023-08-01T11:03:13+00:00" }));
+        std::fs::write(dir.join("launcher_profiles.json"), json!({
+            "profiles": {
+                "a": { "type": "latest-release", "lastVersionId": "latest-release" },
+                "b": { "name": "Snapshots", "type": "latest-snapshot" },
+                "c": { "name": "Old", "type": "custom", "lastVersionId": "1.19.4", "gameDir": dir.join("old") },
+                "d": { "name": "Missing", "type": "custom", "lastVersionId": "1.16.5" },
+                "e": { "name": "Home", "type": "custom", "lastVersionId": "1.20.1", "gameDir": dir },
+            },
+        }).to_string()).unwrap();
+
+        let profiles = read_profiles(&dir).unwrap();
+        let summary: Vec<_> = profiles.iter()
+            .map(|profile| (profile.key.as_str(), profile.name.as_str(), profile.version_id.as_str()))
+            .collect();
+        assert_eq!(summary, [
+            ("a", "1.20.1", "1.20.1"),
+            ("e", "Home", "1.20.1"),
+            ("c", "Old", "1.19.4"),
+            ("b", "Snapshots", "23w31a"),
+        ]);
+        assert_eq!(profiles[1].game_dir, None);
+        assert_eq!(profiles[2].game_dir, Some(dir.join("old")));
+    }
+
+    #[test]
+    fn imports_the_inheritance_chain() {
+        let dir = scratch_dir("official-import");
+        let (minecraft_dir, game_dir) = (dir.join(".minecraft"), dir.join("instance"));
+        install(&minecraft_dir, json!({ "id": "1.20.1", "type": "release" }));
+        install(&minecraft_dir, json!({ "id": "1.20.1-forge-47.2.0", "inheritsFrom": "1.20.1" }));
+        install(&minecraft_dir, json!({ "id": "1.19.4", "type": "release" }));
+        std::fs::write(minecraft_dir.join("versions").join("1.20.1").join("1.20.1.jar"), b"jar").unwrap();
+        let library = Path::new("libraries").join("net").join("minecraftforge").join("forge.jar");
+        std::fs::create_dir_all(minecraft_dir.join(&library).parent().unwrap()).unwrap();
+        std::fs::write(minecraft_dir.join(&library), b"forge").unwrap();
+
+        import_version(&minecraft_dir, "1.20.1-forge-47.2.0", &game_dir).unwrap();
+        let versions = game_dir.join("versions");
+        assert!(versions.join("1.20.1-forge-47.2.0").join("1.20.1-forge-47.2.0.json").is_file());
+        assert!(versions.join("1.20.1").join("1.20.1.json").is_file());
+        assert_eq!(std::fs::read(versions.join("1.20.1").join("1.20.1.jar")).unwrap(), b"jar");
+        assert!(!versions.join("1.19.4").exists());
+        assert_eq!(std::fs::read(game_dir.join(&library)).unwrap(), b"forge");
+        assert!(!game_dir.join("assets").exists());
+    }
+
+    #[test]
+    fn needs_every_parent_installed() {
+        let dir = scratch_dir("official-missing");
+        install(&dir, json!({ "id": "fabric", "inheritsFrom": "1.20.1" }));
+
+        let error = import_version(&dir, "fabric", dir.join("instance")).unwrap_err().to_string();
+        assert!(error.starts_with("Version 1.20.1 isn't installed in"));
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::download::{Queue, Task};
+use crate::util::file_sha512;
+
+pub const MODRINTH_API_URL: &str = "https://api.modrinth.com";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SearchResult {
+    pub hits: Vec<ProjectHit>,
+    pub offset: u32,
+    pub limit: u32,
+    pub total_hits: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ProjectHit {
+    pub project_id: String,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub downloads: u64,
+    pub icon_url: Option<String>,
+    pub project_type: String,
+    #[serde(default)]
+    pub versions: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Version {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub version_number: String,
+    pub version_type: String,
+    pub date_published: String,
+    #[serde(default)]
+    pub game_versions: Vec<String>,
+    #[serde(default)]
+    pub loaders: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    pub files: Vec<VersionFile>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Dependency {
+    pub version_id: Option<String>,
+    pub project_id: Option<String>,
+    pub file_name: Option<String>,
+    pub dependency_type: String, // required, optional, incompatible or embedded
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct VersionFile {
+    pub hashes: FileHashes,
+    pub url: String,
+    pub filename: String,
+    pub primary: bool,
+    pub size: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FileHashes {
+    pub sha1: String,
+    pub sha512: String,
+}
+
+impl Version {
+    // The file flagged primary, or the first one for versions that flag none
+    pub fn primary_file(&self) -> Option<&VersionFile> {
+        self.files.iter().find(|file| file.primary).or_else(|| self.files.first())
+    }
+}
+
+pub struct Modrinth {
+    pub base_url: String,
+    client: Client,
+}
+
+impl Modrinth {
+    pub fn new(base_url: &str) -> Modrinth {
+        Modrinth {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            // Modrinth asks every client to identify itself
+            client: Client::builder()
+                .user_agent(concat!("mc_launcher_core/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    // Mods matching `query`, optionally only those for a game version and loader
+    pub async fn search(&self, query: &str, game_version: Option<&str>, loader: Option<&str>, offset: u32, limit: u32) -> Result<SearchResult> {
+        let mut facets = vec![vec!["project_type:mod".to_owned()]];
+        if let Some(game_version) = game_version {
+            facets.push(vec![format!("versions:{}", game_version)]);
+        }
+        if let Some(loader) = loader {
+            facets.push(vec![format!("categories:{}", loader)]);
+        }
+
+        let url = format!("{}/v2/search", self.base_url);
+        let result = self.client.get(&url)
+            .query(&[
+                ("query", query.to_owned()),
+                ("facets", serde_json::to_string(&facets)?),
+                ("offset", offset.to_string()),
+                ("limit", limit.to_string()),
+            ])
+            .send().await?
+            .error_for_status()?
+            .json().await?;
+        Ok(result)
+    }
+
+    // Newest first. `project` is an id or a slug
+    pub async fn versions(&self, project: &str, game_version: Option<&str>, loader: Option<&str>) -> Result<Vec<Version>> {
+        let mut query = Vec::new();
+        if let Some(game_version) = game_version {
+            query.push(("game_versions", serde_json::to_string(&[game_version])?));
+        }
+        if let Some(loader) = loader {
+            query.push(("loaders", serde_json::to_string(&[loader])?));
+        }
+
+        let url = format!("{}/v2/project/{}/version", self.base_url, project);
+        let versions = self.client.get(&url).query(&query).send().await?.error_for_status()?.json().await?;
+        Ok(versions)
+    }
+
+    pub async fn version(&self, id: &str) -> Result<Version> {
+        let url = format!("{}/v2/version/{}", self.base_url, id);
+        Ok(self.client.get(&url).send().await?.error_for_status()?.json().await?)
+    }
+
+    // The versions files with these sha512 hashes belong to, unknown ones are left out
+    pub async fn versions_by_hash(&self, sha512: &[String]) -> Result<HashMap<String, Version>> {
+        let url = format!("{}/v2/version_files", self.base_url);
+        let body = serde_json::json!({ "hashes": sha512, "algorithm": "sha512" });
+        Ok(self.client.post(&url).json(&body).send().await?.error_for_status()?.json().await?)
+    }
+
+    // `version` and everything it requires, transitively. A dependency pinned to
+    // a version uses that one, otherwise the newest for the game version and loader
+    pub async fn resolve(&self, version: Version, game_version: &str, loader: &str) -> Result<Vec<Version>> {
+        let mut seen: HashSet<String> = HashSet::new();
+        seen.insert(version.project_id.clone());
+
+        let mut out = Vec::new();
+        let mut pending = VecDeque::new();
+        pending.push_back(version);
+        while let Some(version) = pending.pop_front() {
+            for dependency in version.dependencies.iter().filter(|dependency| dependency.dependency_type == "required") {
+                let resolved = match (&dependency.version_id, &dependency.project_id) {
+                    (Some(version_id), _) => self.version(version_id).await?,
+                    (None, Some(project_id)) => {
+                        if seen.contains(project_id) {
+                            continue;
+                        }
+                        self.versions(project_id, Some(game_version), Some(loader)).await?
+                            .into_iter()
+                            .next()
+                            .ok_or(anyhow!("{} requires {}, which has no version for {} {}", version.name, project_id, loader, game_version))?
+                    },
+                    (None, None) => continue,
+                };
+                if seen.insert(resolved.project_id.clone()) {
+                    pending.push_back(resolved);
+                }
+            }
+            out.push(version);
+        }
+        Ok(out)
+    }
+}
+
+impl Default for Modrinth {
+    fn default() -> Self {
+        Modrinth::new(MODRINTH_API_URL)
+    }
+}
+
+pub struct ModrinthInstall {
+    pub files: Vec<(PathBuf, String)>, // path, sha512
+}
+
+impl ModrinthInstall {
+    // Queue the primary file of every version into `mods_dir`, skipping the ones
+    // already there. Call `finish` once the queue is done
+    pub fn prepare<P: AsRef<Path>>(versions: &[Version], mods_dir: P, queue: &mut Queue) -> Result<ModrinthInstall> {
+        let mods_dir = mods_dir.as_ref();
+        std::fs::create_dir_all(mods_dir)?;
+
+        let mut files = Vec::new();
+        for version in versions {
+            let file = version.primary_file().ok_or(anyhow!("{} has no files", version.name))?;
+            // only a plain file name, the API is not trusted with paths
+            if file.filename.contains(['/', '\\']) || file.filename.starts_with('.') {
+                bail!("Invalid file name {}", file.filename);
+            }
+            let path = mods_dir.join(&file.filename);
+            let intact = path.is_file() && file_sha512(&path).is_ok_and(|sha512| sha512 == file.hashes.sha512);
+            if !intact {
+                queue.push_task(Task::new(&file.url, &path, file.size).with_sha1(&file.hashes.sha1));
+            }
+            files.push((path, file.hashes.sha512.clone()));
+        }
+        Ok(ModrinthInstall { files })
+    }
+
+    // Check every file against its sha512, removing the ones that don't match
+    pub fn finish(&self) -> Result<()> {
+        let mut corrupt = Vec::new();
+        for (path, sha512) in &self.files {
+            match file_sha512(path) {
+                Ok(actual) if &actual == sha512 => {},
+                _ => {
+                    let _ = std::fs::remove_file(path);
+                    corrupt.push(path.display().to_string());
+                },
+            }
+        }
+        if !corrupt.is_empty() {
+            bail!("{} mod files failed verification: {}", corrupt.len(), corrupt.join(", "));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value as Json, json};
+    use warp::Filter;
+
+    use super::*;
+
+    fn version(id: &str, project_id: &str, dependencies: Json) -> Json {
+        json!({
+            "id": id,
+            "project_id": project_id,
+            "name": project_id,
+            "version_number": "1.0.0",
+            "version_type": "release",
+            "date_published": "2024-01-01T00:00:00Z",
+            "dependencies": dependencies,
+            "files": [],
+        })
+    }
+
+    // sodium needs fabric-api and a pinned indium build, which needs sodium
+    // back; lonely needs a project without a build for the game version
+    fn serve() -> Modrinth {
+        let search = warp::path!("v2" / "search")
+            .and(warp::query::<HashMap<String, String>>())
+            .map(|query: HashMap<String, String>| {
+                let facets: Vec<Vec<String>> = serde_json::from_str(&query["facets"]).unwrap();
+                let hits = if query["query"] == "sodium" && facets.contains(&vec!["versions:1.20.1".to_owned()]) {
+                    json!([{
+                        "project_id": "AANobbMI", "slug": "sodium", "title": "Sodium", "description": "",
+                        "author": "jellysquid3", "downloads": 1, "icon_url": null, "project_type": "mod",
+                    }])
+                } else {
+                    json!([])
+                };
+                warp::reply::json(&json!({ "hits": hits, "offset": query["offset"].parse::<u32>().unwrap(), "limit": 10, "total_hits": 1 }))
+            });
+        let versions = warp::path!("v2" / "project" / String / "version").map(|project: String| {
+            let versions = match project.as_str() {
+                "sodium" => json!([version("sodium-2", "sodium", json!([
+                    { "project_id": "fabric-api", "dependency_type": "required" },
+                    { "project_id": "indium", "version_id": "indium-1", "dependency_type": "required" },
+                    { "project_id": "iris", "dependency_type": "optional" },
+                ]))]),
+                "fabric-api" => json!([version("fabric-api-9", "fabric-api", json!([])), version("fabric-api-8", "fabric-api", json!([]))]),
+                "lonely" => json!([version("lonely-1", "lonely", json!([{ "project_id": "missing", "dependency_type": "required" }]))]),
+                _ => json!([]),
+            };
+            warp::reply::json(&versions)
+        });
+        let pinned = warp::path!("v2" / "version" / "indium-1").map(|| {
+            warp::reply::json(&version("indium-1", "indium", json!([{ "project_id": "sodium", "dependency_type": "required" }])))
+        });
+
+        let (addr, server) = warp::serve(search.or(versions).or(pinned)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Modrinth::new(&format!("http://{}", addr))
+    }
+
+    #[tokio::test]
+    async fn searches_with_facets() {
+        let modrinth = serve();
+
+        let result = modrinth.search("sodium", Some("1.20.1"), Some("fabric"), 20, 10).await.unwrap();
+        assert_eq!(result.hits.len(), 1);
+        assert_eq!(result.hits[0].slug, "sodium");
+        assert_eq!(result.offset, 20);
+
+        assert!(modrinth.search("sodium", Some("1.8.9"), None, 0, 10).await.unwrap().hits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn resolves_required_dependencies_once() {
+        let modrinth = serve();
+        let sodium = modrinth.versions("sodium", Some("1.20.1"), Some("fabric")).await.unwrap().remove(0);
+
+        let resolved = modrinth.resolve(sodium, "1.20.1", "fabric").await.unwrap();
+        let ids: Vec<&str> = resolved.iter().map(|version| version.id.as_str()).collect();
+        assert_eq!(ids, ["sodium-2", "fabric-api-9", "indium-1"]);
+    }
+
+    #[tokio::test]
+    async fn fails_on_a_missing_required_dependency() {
+        let modrinth = serve();
+        let lonely = modrinth.versions("lonely", None, None).await.unwrap().remove(0);
+
+        assert!(modrinth.resolve(lonely, "1.20.1", "fabric").await.is_err());
+    }
+}
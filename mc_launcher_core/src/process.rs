@@ -0,0 +1,212 @@
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::log4j::{Log4jParser, LogRecord};
+
+#[derive(Clone, Copy, Serialize, Debug)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+pub enum ProcessEvent {
+    Started { pid: u32 },
+    Log { stream: OutputStream, record: LogRecord },
+    Exited { code: Option<i32> },
+}
+
+pub struct GameProcess {
+    pub pid: u32,
+    pub start_time: i64,
+
+    child: Arc<Mutex<Child>>,
+    status: Arc<Mutex<Option<ExitStatus>>>,
+}
+
+impl GameProcess {
+    const POLL_DURATION: Duration = Duration::from_millis(200);
+
+    pub fn spawn(mut command: Command) -> Result<(GameProcess, mpsc::UnboundedReceiver<ProcessEvent>)> {
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let pid = child.id();
+        let stdout = child.stdout.take().ok_or(anyhow!("No stdout"))?;
+        let stderr = child.stderr.take().ok_or(anyhow!("No stderr"))?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send(ProcessEvent::Started { pid });
+
+        let readers = vec![
+            spawn_reader(stdout, OutputStream::Stdout, tx.clone()),
+            spawn_reader(stderr, OutputStream::Stderr, tx.clone()),
+        ];
+
+        let process = GameProcess {
+            pid,
+            start_time: Utc::now().timestamp(),
+            child: Arc::new(Mutex::new(child)),
+            status: Arc::new(Mutex::new(None)),
+        };
+
+        let child = process.child.clone();
+        let status = process.status.clone();
+        thread::spawn(move || {
+            let exit = loop {
+                match child.lock().try_wait() {
+                    Ok(Some(exit)) => break Some(exit),
+                    Ok(None) => {},
+                    Err(_) => break None,
+                }
+                thread::sleep(GameProcess::POLL_DURATION);
+            };
+            // let the readers drain so every line arrives before the exit event
+            for reader in readers {
+                let _ = reader.join();
+            }
+            *status.lock() = exit;
+            let _ = tx.send(ProcessEvent::Exited { code: exit.and_then(|exit| exit.code()) });
+        });
+
+        Ok((process, rx))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.lock().is_none()
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.status.lock().and_then(|status| status.code())
+    }
+
+    // graceful asks the game to quit (SIGTERM / WM_CLOSE), otherwise kill it outright
+    pub fn kill(&self, graceful: bool) -> Result<()> {
+        if !self.is_running() {
+            bail!("Process {} has already exited", self.pid);
+        }
+        if !graceful {
+            return self.child.lock().kill().map_err(anyhow::Error::new);
+        }
+
+        let status = if cfg!(target_os="windows") {
+            Command::new("taskkill").args(["/PID", &self.pid.to_string()]).status()?
+        } else {
+            Command::new("kill").args(["-TERM", &self.pid.to_string()]).status()?
+        };
+        if !status.success() {
+            bail!("Failed to stop process {}", self.pid);
+        }
+        Ok(())
+    }
+}
+
+fn spawn_reader<R>(source: R, stream: OutputStream, tx: mpsc::UnboundedSender<ProcessEvent>) -> thread::JoinHandle<()>
+where R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut parser = Log4jParser::new();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf);
+                    let line = line.trim_end_matches(&['\r', '\n'][..]);
+                    // only stdout carries log4j events
+                    let record = match stream {
+                        OutputStream::Stdout => parser.feed(line),
+                        OutputStream::Stderr => Some(LogRecord::raw(line)),
+                    };
+                    if let Some(record) = record {
+                        if tx.send(ProcessEvent::Log { stream, record }).is_err() {
+                            return;
+                        }
+                    }
+                },
+            }
+        }
+        if let Some(record) = parser.flush() {
+            let _ = tx.send(ProcessEvent::Log { stream, record });
+        }
+    })
+}
+
+// the child processes are shell one-liners
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    async fn events_until_exit(events: &mut mpsc::UnboundedReceiver<ProcessEvent>) -> Vec<ProcessEvent> {
+        let mut out = Vec::new();
+        while let Some(event) = tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap() {
+            let exited = matches!(event, ProcessEvent::Exited { .. });
+            out.push(event);
+            if exited {
+                break;
+            }
+        }
+        out
+    }
+
+    fn lines(events: &[ProcessEvent]) -> Vec<(String, String)> {
+        events.iter()
+            .filter_map(|event| match event {
+                ProcessEvent::Log { stream, record } => Some((format!("{:?}", stream), record.message.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn reports_output_and_exit() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo x; echo y >&2; printf 'no newline'; exit 3"]);
+        let (process, mut events) = GameProcess::spawn(command).unwrap();
+        let events = events_until_exit(&mut events).await;
+
+        assert!(matches!(events.first(), Some(ProcessEvent::Started { pid }) if *pid == process.pid));
+        assert!(matches!(events.last(), Some(ProcessEvent::Exited { code: Some(3) })));
+        let mut lines = lines(&events);
+        lines.sort();
+        assert_eq!(lines, [
+            ("Stderr".to_owned(), "y".to_owned()),
+            ("Stdout".to_owned(), "no newline".to_owned()),
+            ("Stdout".to_owned(), "x".to_owned()),
+        ]);
+
+        assert!(!process.is_running());
+        assert_eq!(process.exit_code(), Some(3));
+        assert!(process.kill(false).is_err());
+    }
+
+    #[tokio::test]
+    async fn kills_a_running_game() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo ready; exec sleep 30"]);
+        let (process, mut events) = GameProcess::spawn(command).unwrap();
+        assert!(process.is_running());
+        assert!(matches!(events.recv().await, Some(ProcessEvent::Started { .. })));
+        assert!(matches!(events.recv().await, Some(ProcessEvent::Log { record, .. }) if record.message == "ready"));
+
+        process.kill(true).unwrap();
+        let events = events_until_exit(&mut events).await;
+        // killed by a signal, so no exit code
+        assert!(matches!(events.last(), Some(ProcessEvent::Exited { code: None })));
+        assert!(!process.is_running());
+        assert_eq!(process.exit_code(), None);
+    }
+}
@@ -0,0 +1,292 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use reqwest::Client;
+
+use crate::deserialize::{DownloadItem, RuntimeFile, RuntimeFileDownloads, RuntimeIndex, RuntimeManifest};
+use crate::download::{Mirrors, Queue, Task};
+use crate::modpack::safe_join;
+use crate::util::file_sha1;
+
+pub const RUNTIME_INDEX_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+// Key of this machine in all.json
+pub fn platform() -> Result<&'static str> {
+    let platform = match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => "linux",
+        ("linux", "x86") => "linux-i386",
+        ("windows", "x86_64") => "windows-x64",
+        ("windows", "x86") => "windows-x86",
+        ("windows", "aarch64") => "windows-arm64",
+        ("macos", "x86_64") => "mac-os",
+        ("macos", "aarch64") => "mac-os-arm64",
+        (os, arch) => bail!("No Mojang Java runtime for {} {}", os, arch),
+    };
+    Ok(platform)
+}
+
+pub struct RuntimeInstall {
+    pub component: String,
+    pub version: String,
+    pub home: PathBuf,
+
+    files: Vec<(PathBuf, String, bool)>, // path, sha1, executable
+    links: Vec<(PathBuf, String)>, // path, target relative to the link's folder
+}
+
+impl RuntimeInstall {
+    // Fetch the manifests of `component` (e.g. java-runtime-gamma), lay out
+    // directories under `runtime_root/component`, and queue every file that
+    // isn't already in place. Call `finish` once the queue is done
+    pub async fn prepare<P>(client: &Client, mirrors: &Mirrors, component: &str, runtime_root: P, queue: &mut Queue) -> Result<RuntimeInstall>
+    where P: AsRef<Path>,
+    {
+        let index: RuntimeIndex = client.get(mirrors.rewrite(RUNTIME_INDEX_URL)).send().await?.error_for_status()?.json().await?;
+        let build = index
+            .get(platform()?)
+            .and_then(|components| components.get(component))
+            .and_then(|builds| builds.first())
+            .ok_or(anyhow!("Java runtime {} isn't available for this platform", component))?;
+
+        let manifest = client.get(mirrors.rewrite(&build.manifest.url)).send().await?.error_for_status()?.bytes().await?;
+        let sha1 = sha1::Sha1::from(&manifest[..]).digest().to_string();
+        if !build.manifest.sha1.is_empty() && sha1 != build.manifest.sha1 {
+            bail!("Manifest of Java runtime {} doesn't match its checksum", component);
+        }
+        let manifest: RuntimeManifest = serde_json::from_slice(&manifest)?;
+        let home = runtime_root.as_ref().join(component);
+
+        let mut files = Vec::new();
+        let mut links = Vec::new();
+        for (name, file) in manifest.files {
+            let path = safe_join(&home, &name)?;
+            match file {
+                RuntimeFile::Directory => std::fs::create_dir_all(&path)?,
+                RuntimeFile::Link { target } => links.push((path, target)),
+                RuntimeFile::File { executable, downloads } => {
+                    let intact = path.is_file() && file_sha1(&path).is_ok_and(|sha1| sha1 == downloads.raw.sha1);
+                    if !intact {
+                        if let Some(parent) = path.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        let (download, packed) = pick_download(&downloads);
+                        let target = if packed { lzma_path(&path) } else { path.clone() };
+                        queue.push_task(Task::new(&download.url, &target, download.size).with_sha1(&download.sha1));
+                    }
+                    files.push((path, downloads.raw.sha1, executable));
+                },
+            }
+        }
+
+        Ok(RuntimeInstall {
+            component: component.to_owned(),
+            version: build.version.name.clone(),
+            home,
+            files,
+            links,
+        })
+    }
+
+    // Unpack lzma downloads, verify every file against the manifest, restore
+    // executable bits and create the links
+    pub fn finish(&self) -> Result<()> {
+        let mut corrupt = Vec::new();
+        for (path, sha1, executable) in &self.files {
+            let packed = lzma_path(path);
+            if packed.is_file() {
+                if let Err(e) = unpack_lzma(&packed, path) {
+                    corrupt.push(format!("{} ({})", path.display(), e));
+                    continue;
+                }
+            }
+            match file_sha1(path) {
+                Ok(actual) if &actual == sha1 => {},
+                _ => {
+                    corrupt.push(path.display().to_string());
+                    continue;
+                },
+            }
+            #[cfg(unix)]
+            if *executable {
+                use std::os::unix::fs::PermissionsExt;
+                let mut permissions = std::fs::metadata(path)?.permissions();
+                permissions.set_mode(permissions.mode() | 0o755);
+                std::fs::set_permissions(path, permissions)?;
+            }
+            #[cfg(not(unix))]
+            let _ = executable;
+        }
+        if !corrupt.is_empty() {
+            bail!("{} runtime files failed verification: {}", corrupt.len(), corrupt.join(", "));
+        }
+
+        for (path, target) in &self.links {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if path.symlink_metadata().is_ok() {
+                std::fs::remove_file(path)?;
+            }
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(target, path)?;
+            // no symlinks without extra privileges, a copy does as well
+            #[cfg(not(unix))]
+            {
+                let source = path.parent().map_or_else(|| PathBuf::from(target), |parent| parent.join(target));
+                if let Err(e) = std::fs::copy(&source, path) {
+                    eprintln!("Can't copy {} to {}: {}", source.display(), path.display(), e);
+                }
+            }
+        }
+
+        std::fs::write(self.home.join(".version"), &self.version)?;
+        Ok(())
+    }
+}
+
+// The download to fetch: the lzma one, flagged true, when it's smaller than
+// the raw file
+fn pick_download(downloads: &RuntimeFileDownloads) -> (&DownloadItem, bool) {
+    match &downloads.lzma {
+        Some(lzma) if lzma.size < downloads.raw.size => (lzma, true),
+        _ => (&downloads.raw, false),
+    }
+}
+
+// Where the lzma download of `path` is kept until `finish` unpacks it
+fn lzma_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lzma");
+    PathBuf::from(name)
+}
+
+fn unpack_lzma(packed: &Path, path: &Path) -> Result<()> {
+    use std::io::Write;
+
+    let mut reader = std::io::BufReader::new(std::fs::File::open(packed)?);
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let result = lzma_rs::lzma_decompress(&mut reader, &mut writer)
+        .map_err(|e| anyhow!("Can't decompress {}: {:?}", packed.display(), e))
+        .and_then(|_| Ok(writer.flush()?));
+    std::fs::remove_file(packed)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use serde_json::{Value as Json, json};
+    use tokio::time::Duration;
+    use warp::Filter;
+
+    use super::*;
+    use crate::download::{MirrorRule, wait};
+    use crate::test_util::scratch_dir;
+
+    const JAVA: &[u8] = b"#!/bin/sh\necho java\n";
+    // `release` as lzma-alone, made with Python's lzma module since lzma-rs
+    // only writes uncompressed streams
+    const RELEASE_LZMA: &[u8] = b"\x5d\x00\x00\x00\x04\xff\xff\xff\xff\xff\xff\xff\xff\x00\x25\x10\x47\x11\x60\x44\x17\x51\xb8\xf9\xdd\x3a\xfc\x8a\xa2\xd7\x47\xe6\x54\xf3\x94\xdb\x69\xf0\xec\xcd\x5c\x7d\x78\x75\x1f\x14\x4f\xff\xe8\xbc\x2f\xff\xff\xf7\x5b\x80\x00";
+
+    fn release() -> Vec<u8> {
+        "JAVA_VERSION=\"17.0.8\"\n".repeat(64).into_bytes()
+    }
+
+    fn download(data: &[u8], url: String) -> Json {
+        json!({ "sha1": sha1::Sha1::from(data).digest().to_string(), "size": data.len(), "url": url })
+    }
+
+    fn manifest(port: u16) -> String {
+        json!({ "files": {
+            "bin": { "type": "directory" },
+            "bin/java": { "type": "file", "executable": true, "downloads": {
+                "raw": download(JAVA, format!("http://127.0.0.1:{}/raw/java", port)),
+            }},
+            "release": { "type": "file", "executable": false, "downloads": {
+                "raw": download(&release(), format!("http://127.0.0.1:{}/raw/release", port)),
+                "lzma": download(RELEASE_LZMA, format!("http://127.0.0.1:{}/lzma/release", port)),
+            }},
+            "legal/java.base/LICENSE": { "type": "link", "target": "../../release" },
+        }}).to_string()
+    }
+
+    // all.json behind a launchermeta mirror, the manifest and its files
+    fn serve() -> Mirrors {
+        let port = Arc::new(AtomicU16::new(0));
+        let index_port = port.clone();
+        let manifest_port = port.clone();
+        let index = warp::path!("v1" / "products" / "java-runtime" / String / "all.json").map(move |_: String| {
+            let port = index_port.load(Ordering::SeqCst);
+            warp::reply::json(&json!({ platform().unwrap(): { "java-runtime-gamma": [{
+                "manifest": download(manifest(port).as_bytes(), format!("http://127.0.0.1:{}/manifest.json", port)),
+                "version": { "name": "17.0.8", "released": "2023-07-25T18:00:00+00:00" },
+            }]}}))
+        });
+        let manifest_file = warp::path!("manifest.json").map(move || manifest(manifest_port.load(Ordering::SeqCst)));
+        // release is only served as lzma, the smaller of its two downloads
+        let raw = warp::path!("raw" / "java").map(|| JAVA);
+        let packed = warp::path!("lzma" / String).map(|_: String| RELEASE_LZMA);
+
+        let (addr, server) = warp::serve(index.or(manifest_file).or(raw).or(packed)).bind_ephemeral(([127, 0, 0, 1], 0));
+        port.store(addr.port(), Ordering::SeqCst);
+        tokio::spawn(server);
+        Mirrors { rules: vec![MirrorRule { from: "launchermeta.mojang.com".to_owned(), to: format!("http://{}", addr) }] }
+    }
+
+    #[test]
+    fn parses_the_manifest() {
+        let manifest: RuntimeManifest = serde_json::from_str(&manifest(80)).unwrap();
+        assert_eq!(manifest.files.len(), 4);
+        assert!(matches!(manifest.files["bin"], RuntimeFile::Directory));
+        assert!(matches!(&manifest.files["legal/java.base/LICENSE"], RuntimeFile::Link { target } if target == "../../release"));
+        match &manifest.files["bin/java"] {
+            RuntimeFile::File { executable, downloads } => {
+                assert!(*executable);
+                assert_eq!(downloads.raw.url, "http://127.0.0.1:80/raw/java");
+                assert!(downloads.lzma.is_none());
+            },
+            _ => panic!("bin/java isn't a file"),
+        }
+    }
+
+    #[test]
+    fn prefers_the_smaller_download() {
+        let item = |size: u64, url: &str| DownloadItem { size, url: url.to_owned(), ..Default::default() };
+        let downloads = RuntimeFileDownloads { raw: item(100, "raw"), lzma: Some(item(40, "lzma")) };
+        assert!(matches!(pick_download(&downloads), (download, true) if download.url == "lzma"));
+
+        let downloads = RuntimeFileDownloads { raw: item(10, "raw"), lzma: Some(item(40, "lzma")) };
+        assert!(matches!(pick_download(&downloads), (download, false) if download.url == "raw"));
+        let downloads = RuntimeFileDownloads { raw: item(100, "raw"), lzma: None };
+        assert!(matches!(pick_download(&downloads), (download, false) if download.url == "raw"));
+        assert_eq!(lzma_path(Path::new("bin/java")), Path::new("bin/java.lzma"));
+    }
+
+    #[tokio::test]
+    async fn installs_a_runtime() {
+        let mirrors = serve();
+        let runtime_root = scratch_dir("runtime-install");
+
+        let mut queue = Queue::new(1024, 4, None, Duration::from_millis(10));
+        let install = RuntimeInstall::prepare(&Client::new(), &mirrors, "java-runtime-gamma", &runtime_root, &mut queue).await.unwrap();
+        let queue = queue.run_in_background();
+        wait(&queue).await;
+        assert_eq!(queue.lock().failed(), 0);
+        install.finish().unwrap();
+
+        let home = runtime_root.join("java-runtime-gamma");
+        assert_eq!(install.version, "17.0.8");
+        assert_eq!(std::fs::read(home.join("bin/java")).unwrap(), JAVA);
+        assert_eq!(std::fs::read(home.join("release")).unwrap(), release());
+        assert!(!home.join("release.lzma").exists());
+        assert_eq!(std::fs::read(home.join("legal/java.base/LICENSE")).unwrap(), release());
+        assert_eq!(std::fs::read_to_string(home.join(".version")).unwrap(), "17.0.8");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(home.join("bin/java")).unwrap().permissions().mode() & 0o111, 0o111);
+        }
+    }
+}
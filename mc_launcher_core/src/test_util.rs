@@ -0,0 +1,26 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use zip::write::FileOptions;
+
+// An empty directory under the temp dir, unique to this test process
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mc_launcher_core-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// A zip at `path` holding `entries` as name and content, names kept verbatim
+pub fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+    for (name, data) in entries {
+        writer.start_file(*name, FileOptions::default()).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap();
+}
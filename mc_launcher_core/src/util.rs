@@ -1,13 +1,18 @@
-use std::path::Path;
+use std::cmp::Ordering;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 use std::fs::*;
 
 use zip;
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 
-async fn extract_zip<P>(zip: P, extract_path: P) -> Result<()>
+// Extract `zip` into `extract_path`, skipping entries under any `exclude` prefix.
+// Returns the extracted files
+pub fn extract_zip<P, Q>(zip: P, extract_path: Q, exclude: &[String]) -> Result<Vec<PathBuf>>
 where 
-        P: AsRef<Path>, {
+        P: AsRef<Path>,
+        Q: AsRef<Path>, {
     let zip_file = zip.as_ref();
     let extract_file = extract_path.as_ref();
 
@@ -16,16 +21,192 @@ where
     }
 
     if !extract_file.exists() {
-        std::fs::create_dir_all(extract_file.parent().ok_or(anyhow!("No parent dir"))?)?;
+        std::fs::create_dir_all(extract_file)?;
     }
 
     let file = File::open(zip_file)?;
     let mut archive = zip::ZipArchive::new(file)?;
+    let mut extracted = Vec::new();
 
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
 
+        if exclude.iter().any(|prefix| file.name().starts_with(prefix.as_str())) {
+            continue;
+        }
+        // Reject absolute paths and `..` components (zip slip)
+        let relative = match file.enclosed_name() {
+            Some(path) => path.to_path_buf(),
+            None => bail!("Unsafe path {} in {}", file.name(), zip_file.display()),
+        };
+        let out_path = extract_file.join(relative);
+
+        if file.is_dir() {
+            create_dir_all(&out_path)?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mut out = File::create(&out_path)?;
+        std::io::copy(&mut file, &mut out)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = file.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            set_permissions(&out_path, Permissions::from_mode(mode))?;
+        }
+        extracted.push(out_path);
+    }
+
+    Ok(extracted)
+}
+
+pub fn file_sha1<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = sha1::Sha1::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.digest().to_string())
+}
+
+pub fn file_sha512<P: AsRef<Path>>(path: P) -> Result<String> {
+    use sha2::Digest;
+
+    let mut file = File::open(path)?;
+    let mut hasher = sha2::Sha512::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
     }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Mirror the tree under `src` into `dst`, hard linking where possible
+pub fn link_or_copy_dir<P, Q>(src: P, dst: Q) -> Result<()>
+where
+        P: AsRef<Path>,
+        Q: AsRef<Path>, {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    create_dir_all(dst)?;
 
+    for entry in read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            link_or_copy_dir(entry.path(), &target)?;
+        }
+        else {
+            if target.exists() {
+                remove_file(&target)?;
+            }
+            if hard_link(entry.path(), &target).is_err() {
+                copy(entry.path(), &target)?;
+            }
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+enum VersionPart<'a> {
+    Number(u64),
+    Text(&'a str),
+}
+
+fn version_parts(version: &str) -> Vec<VersionPart<'_>> {
+    let mut parts = Vec::new();
+    for segment in version.split(['.', '-', '_', '+']) {
+        let mut rest = segment;
+        while !rest.is_empty() {
+            let is_digit = rest.starts_with(|c: char| c.is_ascii_digit());
+            let end = rest
+                .find(|c: char| c.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (token, tail) = rest.split_at(end);
+            if is_digit {
+                parts.push(VersionPart::Number(token.parse().unwrap_or(u64::MAX)));
+            }
+            else {
+                parts.push(VersionPart::Text(token));
+            }
+            rest = tail;
+        }
+    }
+    parts
+}
+
+// Loose maven-like ordering: numbers compare numerically, and a qualifier such
+// as "beta" or "SNAPSHOT" sorts below the plain release ("1.0-beta" < "1.0")
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let a = version_parts(a);
+    let b = version_parts(b);
+
+    for i in 0..std::cmp::max(a.len(), b.len()) {
+        let ordering = match (a.get(i), b.get(i)) {
+            (Some(VersionPart::Number(x)), Some(VersionPart::Number(y))) => x.cmp(y),
+            (Some(VersionPart::Number(_)), Some(VersionPart::Text(_))) => Ordering::Greater,
+            (Some(VersionPart::Text(_)), Some(VersionPart::Number(_))) => Ordering::Less,
+            (Some(VersionPart::Text(x)), Some(VersionPart::Text(y))) => x.to_lowercase().cmp(&y.to_lowercase()),
+            (Some(VersionPart::Number(_)), None) => Ordering::Greater,
+            (Some(VersionPart::Text(_)), None) => Ordering::Less,
+            (None, Some(VersionPart::Number(_))) => Ordering::Less,
+            (None, Some(VersionPart::Text(_))) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{scratch_dir, write_zip};
+
+    #[test]
+    fn extracts_all_but_excluded_entries() {
+        let dir = scratch_dir("util-extract");
+        write_zip(&dir.join("natives.jar"), &[
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0"),
+            ("liblwjgl.so", b"elf"),
+            ("linux/x64/libglfw.so", b"elf"),
+        ]);
+
+        let out = dir.join("out");
+        let extracted = extract_zip(dir.join("natives.jar"), &out, &["META-INF/".to_owned()]).unwrap();
+        assert_eq!(extracted, [out.join("liblwjgl.so"), out.join("linux/x64/libglfw.so")]);
+        assert_eq!(std::fs::read(out.join("linux/x64/libglfw.so")).unwrap(), b"elf");
+        assert!(!out.join("META-INF").exists());
+    }
+
+    #[test]
+    fn rejects_entries_outside_the_target() {
+        let dir = scratch_dir("util-zip-slip");
+        write_zip(&dir.join("evil.jar"), &[("../evil.txt", b"pwned")]);
+
+        let error = extract_zip(dir.join("evil.jar"), dir.join("out"), &[]).unwrap_err();
+        assert!(error.to_string().starts_with("Unsafe path ../evil.txt"));
+        assert!(!dir.join("evil.txt").exists());
+    }
+
+    #[test]
+    fn needs_an_existing_zip() {
+        let dir = scratch_dir("util-missing");
+        assert!(extract_zip(dir.join("missing.jar"), dir.join("out"), &[]).is_err());
+    }
+}
@@ -1,26 +1,36 @@
 use parking_lot::Mutex;
 use reqwest::Client;
+use serde::Serialize;
 use tokio::time::Duration;
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use mc_launcher_core::config::LauncherConfig;
 use mc_launcher_core::download::*;
 use mc_launcher_core::instance::Instance;
 use mc_launcher_core::deserialize::{AssetsIndex, VersionManifest};
+use mc_launcher_core::loaders::queue_libraries;
+use mc_launcher_core::util::file_sha1;
 
 use crate::state::MainState;
 use crate::error::{Result, SerializedError};
+use crate::statics::{self, CONFIG_FILE, DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS};
+
+const VERSION_MANIFEST_URL: &str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+const ASSET_OBJECTS_URL: &str = "https://resources.download.minecraft.net";
 
 #[tauri::command]
-pub async fn download_json(state: tauri::State<'_, MainState>, version_id: String) -> Result<()> {
+pub async fn download_json(state: tauri::State<'_, Mutex<MainState>>, version_id: String) -> Result<()> {
     let CHUNK_SIZE = 3000000;
     let PARALLELS = 64;
     let POLL_DURATION = Duration::from_millis(100);
     let VERSION_ROOT = "versions/";
     let LIBRARY_ROOT = "libraries/";
     let ASSETS_ROOT = "assets/";
-    let MANIFEST_URL = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-    let RESOURCE_URL = "http://resources.download.minecraft.net";
+    let mirrors = load_mirrors().await;
+    let MANIFEST_URL = &mirrors.rewrite("https://launchermeta.mojang.com/mc/game/version_manifest.json");
+    let RESOURCE_URL = &mirrors.rewrite("http://resources.download.minecraft.net");
 
     // let (progress_sender, mut progress_receiver) = mpsc::channel(PARALLELS);
     // let (controller_sender, mut controller_receiver) = mpsc::channel(5);
@@ -54,7 +64,7 @@ pub async fn download_json(state: tauri::State<'_, MainState>, version_id: Strin
             let mut url: String = String::new();
             for version in manifest.versions {
                 if version.id == version_id {
-                    url = version.url;
+                    url = mirrors.rewrite(&version.url);
                 }
             }
             if url.is_empty() {
@@ -71,7 +81,7 @@ pub async fn download_json(state: tauri::State<'_, MainState>, version_id: Strin
                 let mut url: String = String::new();
                 for version in manifest.versions {
                     if version.id == version_id {
-                        url = version.url;
+                        url = mirrors.rewrite(&version.url);
                     }
                 }
                 if url.is_empty() {
@@ -88,7 +98,7 @@ pub async fn download_json(state: tauri::State<'_, MainState>, version_id: Strin
 
     let assets: AssetsIndex =
         if !assets_index.is_file() {
-            Task::new(&instance.assets_index.url, &assets_index, 0).download_file_std(&client).await?;
+            Task::new(&mirrors.rewrite(&instance.assets_index.url), &assets_index, 0).download_file_std(&client).await?;
             serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(&assets_index)?))?
         }
         else {
@@ -96,7 +106,7 @@ pub async fn download_json(state: tauri::State<'_, MainState>, version_id: Strin
                 r
             }
             else {
-                Task::new(&instance.assets_index.url, &assets_index, 0).download_file_std(&client).await?;
+                Task::new(&mirrors.rewrite(&instance.assets_index.url), &assets_index, 0).download_file_std(&client).await?;
                 serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(&assets_index)?))?
             }
         };
@@ -108,14 +118,180 @@ pub async fn check_json() {
 
 }
 
+// Everything vanilla `version_id` needs to launch: its version JSON from the
+// manifest, the client jar, libraries and assets. Files already in place are
+// kept, so loaders can call this before stacking their profile on top
+pub async fn install_vanilla(state: &Mutex<MainState>, version_id: &str) -> Result<()> {
+    let game_dir = std::env::current_dir()?;
+    let mirrors = load_mirrors().await;
+    let client = Client::new();
+
+    let version_file = game_dir.join(statics::VERSION_ROOT).join(version_id).join(format!("{}.json", version_id));
+    let instance: Instance = match read_json(&version_file) {
+        Some(instance) => instance,
+        None => {
+            let manifest: VersionManifest = client.get(mirrors.rewrite(VERSION_MANIFEST_URL)).send().await?.error_for_status()?.json().await?;
+            let version = manifest.versions.into_iter()
+                .find(|version| version.id == version_id)
+                .ok_or(SerializedError::from(format!("{} isn't a Minecraft version", version_id)))?;
+            fetch_file(&client, &mirrors.rewrite(&version.url), &version_file).await?;
+            read_json(&version_file).ok_or(SerializedError::from(format!("Version JSON of {} can't be read", version_id)))?
+        }
+    };
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+
+    let client_jar = &instance.main_downloads.client;
+    let jar = game_dir.join(statics::VERSION_ROOT).join(version_id).join(format!("{}.jar", version_id));
+    if !file_sha1(&jar).map_or(false, |sha1| sha1 == client_jar.sha1) {
+        queue.push_task(Task::new(&client_jar.url, &jar, client_jar.size).with_sha1(&client_jar.sha1));
+    }
+    queue_libraries(&instance.libraries, game_dir.join(statics::LIBRARY_ROOT), &mut queue);
+
+    let assets_root = game_dir.join(statics::ASSETS_ROOT);
+    let index_file = assets_root.join("indexes").join(format!("{}.json", instance.assets_index.id));
+    if !file_sha1(&index_file).map_or(false, |sha1| sha1 == instance.assets_index.sha1) {
+        fetch_file(&client, &mirrors.rewrite(&instance.assets_index.url), &index_file).await?;
+    }
+    let assets: AssetsIndex = read_json(&index_file)
+        .ok_or(SerializedError::from(format!("Asset index {} can't be read", instance.assets_index.id)))?;
+    for object in assets.objects.0 {
+        let relative = format!("{}/{}", object.hash.get(..2).unwrap_or_default(), object.hash);
+        let path = assets_root.join("objects").join(&relative);
+        if !path.is_file() {
+            queue.push_task(Task::new(&format!("{}/{}", ASSET_OBJECTS_URL, relative), &path, object.size).with_sha1(&object.hash));
+        }
+    }
+
+    run_queue(state, version_id, queue).await
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    std::fs::read(path).ok().and_then(|data| serde_json::from_slice(&data).ok())
+}
+
+async fn fetch_file(client: &Client, url: &str, path: &Path) -> Result<()> {
+    let data = client.get(url).send().await?.error_for_status()?.bytes().await?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, &data)?;
+    Ok(())
+}
+
+// The download mirrors set in the launcher config, none if it can't be read
+pub async fn load_mirrors() -> Mirrors {
+    LauncherConfig::load(CONFIG_FILE).await.unwrap_or_default().mirrors()
+}
+
+// Run a queue through the configured mirrors, fallbacks and retry settings
+// until it settles, listed as `title` so it can be paused or aborted in the
+// meantime
+pub async fn run_listed(state: &Mutex<MainState>, title: &str, mut queue: Queue) -> Arc<Mutex<Queue>> {
+    let config = LauncherConfig::load(CONFIG_FILE).await.unwrap_or_default();
+    queue.mirrors = config.mirrors();
+    queue.retry = config.download_retry.policy();
+    for (base_url, alternate) in config.fallbacks() {
+        queue.add_fallback(&base_url, &alternate);
+    }
+    let handle = queue.run_in_background();
+    let id = state.lock().download_state.add(title, handle.clone());
+    wait(&handle).await;
+    state.lock().download_state.queues.remove(&id);
+    handle
+}
+
+// Run a queue to completion, failing if it was aborted, any part couldn't be
+// downloaded or a file still doesn't match its sha1
+pub async fn run_queue(state: &Mutex<MainState>, title: &str, queue: Queue) -> Result<()> {
+    let handle = run_listed(state, title, queue).await;
+
+    let queue = handle.lock();
+    if queue.is_aborted() {
+        return Err(SerializedError::from(format!("{} was aborted", title)));
+    }
+    if !queue.corrupt().is_empty() {
+        let corrupt: Vec<String> = queue.corrupt().iter().map(|path| path.display().to_string()).collect();
+        return Err(SerializedError::from(format!("{} files are corrupt: {}", corrupt.len(), corrupt.join(", "))));
+    }
+    let failed = queue.failed();
+    if failed > 0 {
+        return Err(SerializedError::from(format!("{} downloads failed", failed)));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct DownloadProgress {
+    id: usize,
+    title: String,
+    total: usize,
+    completed: usize,
+    failed: usize,
+    speed: f64, // bytes per second
+    paused: bool,
+}
+
+#[tauri::command]
+pub async fn list_downloads(state: tauri::State<'_, Mutex<MainState>>) -> Result<Vec<DownloadProgress>> {
+    let lock = state.lock();
+    let mut downloads: Vec<DownloadProgress> = lock.download_state.queues
+        .iter()
+        .map(|(id, (title, handle))| {
+            let queue = handle.lock();
+            DownloadProgress {
+                id: *id,
+                title: title.clone(),
+                total: queue.total(),
+                completed: queue.completed(),
+                failed: queue.failed(),
+                speed: queue.speed(),
+                paused: queue.is_paused(),
+            }
+        })
+        .collect();
+    downloads.sort_by_key(|download| download.id);
+    Ok(downloads)
+}
+
+#[tauri::command]
+pub async fn pause_download(state: tauri::State<'_, Mutex<MainState>>, id: usize) -> Result<()> {
+    state.lock().download_state.control(id, ControlSignal::Pause)
+}
+
+#[tauri::command]
+pub async fn resume_download(state: tauri::State<'_, Mutex<MainState>>, id: usize) -> Result<()> {
+    state.lock().download_state.control(id, ControlSignal::Continue)
+}
+
+#[tauri::command]
+pub async fn abort_download(state: tauri::State<'_, Mutex<MainState>>, id: usize) -> Result<()> {
+    state.lock().download_state.control(id, ControlSignal::Abort)
+}
+
 pub struct DownloadState {
-    queues: Vec<Arc<Mutex<Queue>>>,
+    queues: HashMap<usize, (String, Arc<Mutex<Queue>>)>,
+    next_id: usize,
 }
 
 impl DownloadState {
     pub fn new() -> DownloadState{
         DownloadState {
-            queues: Vec::new(),
+            queues: HashMap::new(),
+            next_id: 0,
         }
     }
-}
\ No newline at end of file
+
+    fn add(&mut self, title: &str, handle: Arc<Mutex<Queue>>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queues.insert(id, (title.to_owned(), handle));
+        id
+    }
+
+    fn control(&self, id: usize, signal: ControlSignal) -> Result<()> {
+        let (_, handle) = self.queues.get(&id).ok_or(SerializedError::from("No such download"))?;
+        handle.lock().control(signal);
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use mc_launcher_core::crash::{analyze, CrashAnalysis};
+use mc_launcher_core::deserialize::Classpath;
+use mc_launcher_core::download::Task;
+use mc_launcher_core::instance::{inherit, Instance};
+use mc_launcher_core::java;
+use mc_launcher_core::launch::{extract_natives, LaunchBuilder};
+use mc_launcher_core::log4j::LogRecord;
+use mc_launcher_core::process::{GameProcess, OutputStream, ProcessEvent};
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::download::load_mirrors;
+use crate::error::{Result, SerializedError};
+use crate::mods::mod_problems;
+use crate::state::MainState;
+use crate::statics::{ASSETS_ROOT, INSTANCES_ROOT, LIBRARY_ROOT, NATIVES_CACHE_ROOT, RUNTIME_ROOT, VERSION_ROOT};
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use parking_lot::Mutex;
+
+const LOG_TAIL_LINES: usize = 500;
+
+#[tauri::command]
+pub async fn launch_instance(window: tauri::Window, state: tauri::State<'_, Mutex<MainState>>, version_id: String) -> Result<u32> {
+    let account = {
+        let lock = state.lock();
+        lock.login_state.accounts
+            .get(&lock.login_state.active_uuid)
+            .cloned()
+            .ok_or(SerializedError::from("No active account"))?
+    };
+
+    let game_dir = std::env::current_dir()?;
+    let instance = load_instance(&game_dir, &version_id)?;
+
+    let classpath = Classpath::resolve(
+        &instance.libraries,
+        game_dir.join(LIBRARY_ROOT),
+        game_dir.join(VERSION_ROOT).join(&instance.jar).join(format!("{}.jar", instance.jar)));
+    if !classpath.missing.is_empty() {
+        return Err(SerializedError::from(format!("{} files are missing, please download the version first", classpath.missing.len())));
+    }
+
+    let run_dir = instance_dir(&game_dir, &version_id);
+
+    // catch broken mod sets before the JVM does
+    let fatal: Vec<String> = mod_problems(&run_dir, &instance)?
+        .iter()
+        .filter(|problem| problem.is_fatal())
+        .map(|problem| problem.message())
+        .collect();
+    if !fatal.is_empty() {
+        return Err(SerializedError::from(format!("Mod problems: {}", fatal.join("; "))));
+    }
+
+    let runtimes = java::discover(game_dir.join(RUNTIME_ROOT));
+    let runtime = java::select(&runtimes, instance.java_version.major_version)
+        .ok_or(SerializedError::from(format!("No Java {} or newer found", instance.java_version.major_version)))?;
+
+    let mut builder = LaunchBuilder::new(&instance, &account, &game_dir);
+    builder.classpath = classpath.join();
+    builder.java_path = runtime.path.clone();
+    builder.game_dir = run_dir.clone();
+
+    // structured log4j output, launch without it if the config can't be fetched
+    if let Some(logging) = &instance.logging {
+        let log_file = &logging.client.file;
+        let log_config = game_dir.join(ASSETS_ROOT).join("log_configs").join(&log_file.path);
+        if log_config.is_file() ||
+            Task::new(&load_mirrors().await.rewrite(&log_file.url), &log_config, log_file.size).download_file_std(&Client::new()).await.is_ok() {
+            builder.logging_config = Some(log_config);
+        }
+    }
+    extract_natives(&instance.libraries, &builder.library_root, &builder.natives_dir, game_dir.join(NATIVES_CACHE_ROOT))?;
+
+    let natives_dir = builder.natives_dir.clone();
+    let (process, mut events) = GameProcess::spawn(builder.build()?)?;
+    let pid = process.pid;
+    let start_time = process.start_time;
+
+    state.lock().game_state.running.insert(pid, RunningGame {
+        version_id: version_id.clone(),
+        process,
+    });
+
+    tauri::async_runtime::spawn(async move {
+        let mut tail: VecDeque<String> = VecDeque::with_capacity(LOG_TAIL_LINES);
+
+        while let Some(event) = events.recv().await {
+            let _ = match event {
+                ProcessEvent::Started { pid } => window.emit("game-started", GameStarted {
+                    pid,
+                    version_id: version_id.clone(),
+                }),
+                ProcessEvent::Log { stream, record } => {
+                    if tail.len() >= LOG_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(match &record.throwable {
+                        Some(throwable) => format!("{}\n{}", record.message, throwable),
+                        None => record.message.clone(),
+                    });
+                    window.emit("game-log", GameLog {
+                        pid,
+                        stream,
+                        record,
+                    })
+                },
+                ProcessEvent::Exited { code } => {
+                    let _ = std::fs::remove_dir_all(&natives_dir);
+                    let crash = if code != Some(0) {
+                        let lines: Vec<String> = tail.iter()
+                            .flat_map(|line| line.lines().map(|l| l.to_owned()))
+                            .collect();
+                        Some(analyze(&run_dir, pid, start_time, &lines))
+                    } else {
+                        None
+                    };
+                    window.emit("game-exited", GameExited { pid, code, crash })
+                },
+            };
+        }
+    });
+
+    Ok(pid)
+}
+
+pub fn load_instance(game_dir: &Path, version_id: &str) -> Result<Instance> {
+    Ok(inherit::load(game_dir.join(VERSION_ROOT), version_id)?)
+}
+
+// Where a version runs: its own folder under instances/ when it has one (packs
+// get one), the shared game directory otherwise
+pub fn instance_dir(game_dir: &Path, version_id: &str) -> PathBuf {
+    let dir = game_dir.join(INSTANCES_ROOT).join(version_id);
+    if dir.is_dir() { dir } else { game_dir.to_path_buf() }
+}
+
+#[tauri::command]
+pub async fn kill_instance(state: tauri::State<'_, Mutex<MainState>>, pid: u32, force: bool) -> Result<()> {
+    let lock = state.lock();
+    let game = lock.game_state.running
+        .get(&pid)
+        .ok_or(SerializedError::from("No such process"))?;
+    game.process.kill(!force)?;
+    drop(lock);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_running(state: tauri::State<'_, Mutex<MainState>>) -> Result<Vec<Running>> {
+    let mut lock = state.lock();
+    lock.game_state.running.retain(|_, game| game.process.is_running());
+
+    let res = lock.game_state.running
+        .iter()
+        .map(|(pid, game)| Running {
+            pid: *pid,
+            version_id: game.version_id.clone(),
+            start_time: game.process.start_time,
+        })
+        .collect();
+    drop(lock);
+    Ok(res)
+}
+
+#[derive(Clone, Serialize)]
+pub struct GameStarted {
+    pub pid: u32,
+    pub version_id: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GameLog {
+    pub pid: u32,
+    pub stream: OutputStream,
+    pub record: LogRecord,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GameExited {
+    pub pid: u32,
+    pub code: Option<i32>,
+    pub crash: Option<CrashAnalysis>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Running {
+    pub pid: u32,
+    pub version_id: String,
+    pub start_time: i64,
+}
+
+pub struct RunningGame {
+    pub version_id: String,
+    pub process: GameProcess,
+}
+
+pub struct GameState {
+    pub running: HashMap<u32, RunningGame>,
+}
+
+impl GameState {
+    pub fn new() -> GameState {
+        GameState {
+            running: HashMap::new(),
+        }
+    }
+}
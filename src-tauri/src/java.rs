@@ -0,0 +1,45 @@
+use mc_launcher_core::download::Queue;
+use mc_launcher_core::java::{self, JavaRuntime};
+use mc_launcher_core::runtime::RuntimeInstall;
+use parking_lot::Mutex;
+use reqwest::Client;
+use tokio::time::Duration;
+
+use crate::download::{load_mirrors, run_queue};
+use crate::error::{Result, SerializedError};
+use crate::game::load_instance;
+use crate::state::MainState;
+use crate::statics::{DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, RUNTIME_ROOT};
+
+#[tauri::command]
+pub async fn list_java() -> Result<Vec<JavaRuntime>> {
+    let game_dir = std::env::current_dir()?;
+    Ok(java::discover(game_dir.join(RUNTIME_ROOT)))
+}
+
+#[tauri::command]
+pub async fn select_java(version_id: String) -> Result<Option<JavaRuntime>> {
+    let game_dir = std::env::current_dir()?;
+    let instance = load_instance(&game_dir, &version_id)?;
+
+    let runtimes = java::discover(game_dir.join(RUNTIME_ROOT));
+    Ok(java::select(&runtimes, instance.java_version.major_version).cloned())
+}
+
+// Download the Mojang runtime the version asks for
+#[tauri::command]
+pub async fn install_java(state: tauri::State<'_, Mutex<MainState>>, version_id: String) -> Result<JavaRuntime> {
+    let game_dir = std::env::current_dir()?;
+    let instance = load_instance(&game_dir, &version_id)?;
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    let install = RuntimeInstall::prepare(&Client::new(), &load_mirrors().await, &instance.java_version.component, game_dir.join(RUNTIME_ROOT), &mut queue).await?;
+
+    run_queue(&state, &instance.java_version.component, queue).await?;
+    install.finish()?;
+
+    java::discover(game_dir.join(RUNTIME_ROOT))
+        .into_iter()
+        .find(|runtime| runtime.home.starts_with(&install.home))
+        .ok_or(SerializedError::from("Installed runtime can't be found"))
+}
@@ -0,0 +1,150 @@
+use mc_launcher_core::download::{Queue, Task};
+use mc_launcher_core::instance::mods::ModLoader;
+use mc_launcher_core::java;
+use mc_launcher_core::loaders::{self, fabric::{self, FabricMeta}, forge::{self, ForgeInstaller}, legacy_forge::{self, LegacyForgeInstaller}, quilt::{self, QuiltMeta}};
+use mc_launcher_core::modpack::PackVersion;
+use parking_lot::Mutex;
+use reqwest::Client;
+use tokio::time::Duration;
+
+use crate::download::{install_vanilla, load_mirrors, run_listed, run_queue};
+use crate::error::{Result, SerializedError};
+use crate::game::load_instance;
+use crate::state::MainState;
+use crate::statics::{DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, LIBRARY_ROOT, RUNTIME_ROOT, VERSION_ROOT};
+
+async fn fabric_meta() -> FabricMeta {
+    FabricMeta::new(&load_mirrors().await.rewrite(fabric::FABRIC_META_URL))
+}
+
+async fn quilt_meta() -> QuiltMeta {
+    QuiltMeta::new(&load_mirrors().await.rewrite(quilt::QUILT_META_URL))
+}
+
+#[tauri::command]
+pub async fn list_fabric_loaders(game_version: String) -> Result<Vec<fabric::LoaderVersion>> {
+    Ok(fabric_meta().await.loader_versions(&game_version).await?)
+}
+
+// Returns the id of the new version
+#[tauri::command]
+pub async fn install_fabric(state: tauri::State<'_, Mutex<MainState>>, game_version: String, loader_version: String) -> Result<String> {
+    fabric_version(&state, &game_version, &loader_version).await
+}
+
+async fn fabric_version(state: &Mutex<MainState>, game_version: &str, loader_version: &str) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    let (id, libraries) = fabric_meta().await
+        .install(game_version, loader_version, game_dir.join(VERSION_ROOT))
+        .await?;
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    loaders::queue_libraries(&libraries, game_dir.join(LIBRARY_ROOT), &mut queue);
+    run_queue(state, &id, queue).await?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn list_quilt_loaders(game_version: String) -> Result<Vec<quilt::LoaderVersion>> {
+    Ok(quilt_meta().await.loader_versions(&game_version).await?)
+}
+
+#[tauri::command]
+pub async fn install_quilt(state: tauri::State<'_, Mutex<MainState>>, game_version: String, loader_version: String) -> Result<String> {
+    quilt_version(&state, &game_version, &loader_version).await
+}
+
+async fn quilt_version(state: &Mutex<MainState>, game_version: &str, loader_version: &str) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    let (id, libraries) = quilt_meta().await
+        .install(game_version, loader_version, game_dir.join(VERSION_ROOT))
+        .await?;
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    loaders::queue_libraries(&libraries, game_dir.join(LIBRARY_ROOT), &mut queue);
+    run_queue(state, &id, queue).await?;
+
+    Ok(id)
+}
+
+// Install from a Forge or NeoForge installer jar. The Minecraft version it
+// targets has to be installed first, the processors patch its client jar
+#[tauri::command]
+pub async fn install_forge(state: tauri::State<'_, Mutex<MainState>>, installer_path: String) -> Result<String> {
+    forge_version(&state, &installer_path).await
+}
+
+async fn forge_version(state: &Mutex<MainState>, installer_path: &str) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    if forge::is_legacy(installer_path)? {
+        return install_legacy_forge(state, &game_dir, installer_path).await;
+    }
+    let installer = ForgeInstaller::open(installer_path)?;
+
+    let minecraft = &installer.profile.minecraft;
+    let client_jar = game_dir.join(VERSION_ROOT).join(minecraft).join(format!("{}.jar", minecraft));
+    if !client_jar.is_file() {
+        return Err(SerializedError::from(format!("Minecraft {} has to be installed first", minecraft)));
+    }
+    let major_version = load_instance(&game_dir, minecraft)?.java_version.major_version;
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    installer.prepare(&game_dir, &mut queue)?;
+    run_queue(state, &installer.version_id, queue).await?;
+
+    let runtimes = java::discover(game_dir.join(RUNTIME_ROOT));
+    let runtime = java::select(&runtimes, major_version)
+        .ok_or(SerializedError::from(format!("No Java {} runtime found for the installer", major_version)))?;
+    let java = runtime.path.clone();
+
+    let id = installer.version_id.clone();
+    tokio::task::spawn_blocking(move || installer.run_processors(&game_dir, &java))
+        .await??;
+
+    Ok(id)
+}
+
+async fn install_legacy_forge(state: &Mutex<MainState>, game_dir: &std::path::Path, installer_path: &str) -> Result<String> {
+    let mut installer = LegacyForgeInstaller::open(installer_path)?;
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    installer.prepare(game_dir, &mut queue)?;
+    // libraries the plain download misses are retried as .pack.xz by `finish`
+    let queue = run_listed(state, &installer.version_id, queue).await;
+    if queue.lock().is_aborted() {
+        return Err(SerializedError::from(format!("{} was aborted", installer.version_id)));
+    }
+
+    let runtimes = java::discover(game_dir.join(RUNTIME_ROOT));
+    let unpack200 = legacy_forge::find_unpack200(&runtimes);
+    installer.finish(&Client::new(), &load_mirrors().await, unpack200.as_deref()).await?;
+
+    Ok(installer.version_id)
+}
+
+// Install the vanilla version a pack is built on, then whatever loader it asks
+// for. Returns the version id to build on, the vanilla one for packs without a
+// loader
+pub async fn install_loader(state: &Mutex<MainState>, version: &PackVersion) -> Result<String> {
+    install_vanilla(state, &version.minecraft).await?;
+
+    let (loader, loader_version) = match &version.loader {
+        Some((loader, loader_version)) => (*loader, loader_version.clone()),
+        None => return Ok(version.minecraft.clone()),
+    };
+    let minecraft = version.minecraft.clone();
+
+    let url = match loader {
+        ModLoader::Fabric => return fabric_version(state, &minecraft, &loader_version).await,
+        ModLoader::Quilt => return quilt_version(state, &minecraft, &loader_version).await,
+        ModLoader::NeoForge => forge::neoforge_installer_url(&minecraft, &loader_version),
+        ModLoader::Forge | ModLoader::LegacyForge => forge::forge_installer_url(&minecraft, &loader_version),
+    };
+    let installer = std::env::temp_dir().join(url.rsplit('/').next().unwrap_or("installer.jar"));
+    Task::new(&load_mirrors().await.rewrite(&url), &installer, 0).download_file_std(&Client::new()).await?;
+
+    let result = forge_version(state, &installer.to_string_lossy()).await;
+    let _ = std::fs::remove_file(&installer);
+    result
+}
@@ -8,9 +8,21 @@ pub mod error;
 pub mod state;
 pub mod download;
 pub mod statics;
+pub mod game;
+pub mod java;
+pub mod loader;
+pub mod mods;
+pub mod modrinth;
+pub mod modpack;
 
 use crate::login::{login, login_abort, get_logged, get_logging, get_active, set_active, delete_account};
-use crate::download::download_json;
+use crate::download::{download_json, list_downloads, pause_download, resume_download, abort_download};
+use crate::game::{launch_instance, kill_instance, list_running};
+use crate::java::{list_java, select_java, install_java};
+use crate::loader::{list_fabric_loaders, install_fabric, list_quilt_loaders, install_quilt, install_forge};
+use crate::mods::{list_mods, set_mod_enabled, check_mods};
+use crate::modrinth::{search_modrinth, list_modrinth_versions, install_modrinth};
+use crate::modpack::{import_mrpack, export_mrpack, import_curseforge, import_multimc, list_official_profiles, import_official};
 use crate::state::MainState;
 use parking_lot::Mutex;
 
@@ -22,11 +34,38 @@ fn main() {
             login,
             login_abort,
             download_json,
+            list_downloads,
+            pause_download,
+            resume_download,
+            abort_download,
             get_logged,
             get_logging,
             get_active,
             set_active,
             delete_account,
+            launch_instance,
+            kill_instance,
+            list_running,
+            list_java,
+            select_java,
+            install_java,
+            list_fabric_loaders,
+            install_fabric,
+            list_quilt_loaders,
+            install_quilt,
+            install_forge,
+            list_mods,
+            set_mod_enabled,
+            check_mods,
+            search_modrinth,
+            list_modrinth_versions,
+            install_modrinth,
+            import_mrpack,
+            export_mrpack,
+            import_curseforge,
+            import_multimc,
+            list_official_profiles,
+            import_official,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
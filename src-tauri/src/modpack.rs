@@ -0,0 +1,118 @@
+use mc_launcher_core::config::LauncherConfig;
+use mc_launcher_core::download::Queue;
+use mc_launcher_core::instance::mod_check;
+use mc_launcher_core::modpack::{self, ImportMode, PackVersion, curseforge::{self, CurseForge, CurseForgePack}, mrpack::{self, Mrpack}, multimc::MultiMcInstance, official::{self, LauncherProfile}};
+use mc_launcher_core::modrinth::Modrinth;
+use parking_lot::Mutex;
+use tokio::time::Duration;
+
+use std::path::PathBuf;
+
+use crate::download::run_queue;
+use crate::error::{Result, SerializedError};
+use crate::game::{instance_dir, load_instance};
+use crate::loader::install_loader;
+use crate::state::MainState;
+use crate::statics::{CONFIG_FILE, DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, INSTANCES_ROOT, VERSION_ROOT};
+
+// Create an instance from a .mrpack. Returns its version id
+#[tauri::command]
+pub async fn import_mrpack(state: tauri::State<'_, Mutex<MainState>>, path: String) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    let pack = Mrpack::open(&path)?;
+
+    let parent = install_loader(&state, &pack.version()?).await?;
+    let id = modpack::create_instance(game_dir.join(VERSION_ROOT), &pack.index.name, &parent)?;
+    let pack_dir = game_dir.join(INSTANCES_ROOT).join(&id);
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    let files = pack.prepare(&pack_dir, &mut queue)?;
+    run_queue(&state, &pack.index.name, queue).await?;
+    files.verify()?;
+
+    Ok(id)
+}
+
+// Create an instance from a CurseForge modpack zip. Returns its version id
+#[tauri::command]
+pub async fn import_curseforge(state: tauri::State<'_, Mutex<MainState>>, path: String) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    let pack = CurseForgePack::open(&path)?;
+
+    let config = LauncherConfig::load(CONFIG_FILE).await.unwrap_or_default();
+    let api = CurseForge::new(
+        config.curseforge_api_url.as_deref().unwrap_or(curseforge::CURSEFORGE_API_URL),
+        config.curseforge_api_key.as_deref());
+
+    let parent = install_loader(&state, &pack.version()?).await?;
+    let id = modpack::create_instance(game_dir.join(VERSION_ROOT), &pack.manifest.name, &parent)?;
+    let pack_dir = game_dir.join(INSTANCES_ROOT).join(&id);
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    let files = pack.prepare(&api, &pack_dir, &mut queue).await?;
+    run_queue(&state, &pack.manifest.name, queue).await?;
+    files.verify()?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn export_mrpack(version_id: String, pack_version: String, out_path: String) -> Result<()> {
+    let game_dir = std::env::current_dir()?;
+    let instance = load_instance(&game_dir, &version_id)?;
+    let version = PackVersion {
+        loader: mod_check::detect_loader(&instance.libraries, &instance.jar),
+        minecraft: instance.jar.clone(),
+    };
+
+    mrpack::export(&Modrinth::default(), instance_dir(&game_dir, &version_id), &version_id, &pack_version, &version, &out_path).await?;
+    Ok(())
+}
+
+// Recreate a MultiMC/Prism instance folder here. Returns the new version id
+#[tauri::command]
+pub async fn import_multimc(state: tauri::State<'_, Mutex<MainState>>, path: String, mode: ImportMode) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    let source = MultiMcInstance::open(&path)?;
+
+    let parent = install_loader(&state, &source.version).await?;
+    let id = modpack::create_instance(game_dir.join(VERSION_ROOT), &source.name, &parent)?;
+    let instance_dir = game_dir.join(INSTANCES_ROOT).join(&id);
+    match &source.game_dir {
+        Some(source_dir) => modpack::import_tree(source_dir, &instance_dir, mode, &[])?,
+        None => std::fs::create_dir_all(&instance_dir)?,
+    }
+
+    Ok(id)
+}
+
+fn minecraft_dir(minecraft_dir: Option<String>) -> Result<PathBuf> {
+    minecraft_dir
+        .map(PathBuf::from)
+        .or_else(official::default_minecraft_dir)
+        .ok_or(SerializedError::from("Can't find the official launcher's folder"))
+}
+
+#[tauri::command]
+pub async fn list_official_profiles(minecraft_dir: Option<String>) -> Result<Vec<LauncherProfile>> {
+    Ok(official::read_profiles(self::minecraft_dir(minecraft_dir)?)?)
+}
+
+// Bring an official launcher profile over, reusing its versions, libraries and
+// assets. Returns the new version id
+#[tauri::command]
+pub async fn import_official(minecraft_dir: Option<String>, profile_key: String, mode: ImportMode) -> Result<String> {
+    let game_dir = std::env::current_dir()?;
+    let minecraft_dir = self::minecraft_dir(minecraft_dir)?;
+    let profile = official::read_profiles(&minecraft_dir)?
+        .into_iter()
+        .find(|profile| profile.key == profile_key)
+        .ok_or(SerializedError::from("No such profile"))?;
+
+    official::import_version(&minecraft_dir, &profile.version_id, &game_dir)?;
+    let id = modpack::create_instance(game_dir.join(VERSION_ROOT), &profile.name, &profile.version_id)?;
+    let source_dir = profile.game_dir.as_ref().unwrap_or(&minecraft_dir);
+    modpack::import_tree(source_dir, &game_dir.join(INSTANCES_ROOT).join(&id), mode, official::SKIPPED_ENTRIES)?;
+
+    Ok(id)
+}
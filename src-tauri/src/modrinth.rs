@@ -0,0 +1,42 @@
+use mc_launcher_core::download::Queue;
+use mc_launcher_core::modrinth::{Modrinth, ModrinthInstall, SearchResult, Version};
+use parking_lot::Mutex;
+use tokio::time::Duration;
+
+use crate::download::run_queue;
+use crate::error::Result;
+use crate::state::MainState;
+use crate::statics::{DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, MODS_ROOT};
+
+const SEARCH_PAGE_SIZE: u32 = 20;
+
+#[tauri::command]
+pub async fn search_modrinth(query: String, game_version: Option<String>, loader: Option<String>, offset: u32) -> Result<SearchResult> {
+    Ok(Modrinth::default()
+        .search(&query, game_version.as_deref(), loader.as_deref(), offset, SEARCH_PAGE_SIZE)
+        .await?)
+}
+
+#[tauri::command]
+pub async fn list_modrinth_versions(project: String, game_version: Option<String>, loader: Option<String>) -> Result<Vec<Version>> {
+    Ok(Modrinth::default().versions(&project, game_version.as_deref(), loader.as_deref()).await?)
+}
+
+// Install a Modrinth version and what it requires into mods/. Returns the file names
+#[tauri::command]
+pub async fn install_modrinth(state: tauri::State<'_, Mutex<MainState>>, modrinth_version: String, game_version: String, loader: String) -> Result<Vec<String>> {
+    let modrinth = Modrinth::default();
+    let version = modrinth.version(&modrinth_version).await?;
+    let title = version.name.clone();
+    let versions = modrinth.resolve(version, &game_version, &loader).await?;
+
+    let mut queue = Queue::new(DOWNLOAD_CHUNK_SIZE, DOWNLOAD_PARALLELS, None, Duration::from_millis(100));
+    let install = ModrinthInstall::prepare(&versions, std::env::current_dir()?.join(MODS_ROOT), &mut queue)?;
+    run_queue(&state, &title, queue).await?;
+    install.finish()?;
+
+    Ok(install.files.iter()
+        .filter_map(|(path, _)| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect())
+}
@@ -0,0 +1,43 @@
+use mc_launcher_core::instance::Instance;
+use mc_launcher_core::instance::mod_check::{self, ModProblem};
+use mc_launcher_core::instance::mods::{self, ModFile};
+
+use crate::error::{Result, SerializedError};
+use crate::game::{instance_dir, load_instance};
+use crate::statics::MODS_ROOT;
+
+use std::path::Path;
+
+#[tauri::command]
+pub async fn list_mods(version_id: String) -> Result<Vec<ModFile>> {
+    let game_dir = std::env::current_dir()?;
+    Ok(mods::scan(instance_dir(&game_dir, &version_id).join(MODS_ROOT))?)
+}
+
+// Returns the file name after renaming
+#[tauri::command]
+pub async fn set_mod_enabled(version_id: String, file_name: String, enabled: bool) -> Result<String> {
+    let mods_dir = instance_dir(&std::env::current_dir()?, &version_id).join(MODS_ROOT);
+    let path = mods_dir.join(&file_name);
+    // only plain names, nothing outside mods/
+    if path.parent() != Some(mods_dir.as_path()) {
+        return Err(SerializedError::from("Invalid mod file name"));
+    }
+
+    let renamed = mods::set_enabled(&path, enabled)?;
+    Ok(renamed.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn check_mods(version_id: String) -> Result<Vec<ModProblem>> {
+    let game_dir = std::env::current_dir()?;
+    let instance = load_instance(&game_dir, &version_id)?;
+    mod_problems(&instance_dir(&game_dir, &version_id), &instance)
+}
+
+// What's wrong with the enabled mods for this version's game and loader
+pub fn mod_problems(game_dir: &Path, instance: &Instance) -> Result<Vec<ModProblem>> {
+    let files = mods::scan(game_dir.join(MODS_ROOT))?;
+    let loader = mod_check::detect_loader(&instance.libraries, &instance.jar);
+    Ok(mod_check::check(&files, &instance.jar, loader.as_ref().map(|(loader, version)| (*loader, version.as_str()))))
+}
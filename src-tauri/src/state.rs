@@ -1,8 +1,9 @@
-use crate::{download::DownloadState, login::LoginState};
+use crate::{download::DownloadState, game::GameState, login::LoginState};
 
 pub struct MainState {
     pub login_state: LoginState,
     pub download_state: DownloadState,
+    pub game_state: GameState,
 }
 
 impl MainState {
@@ -10,6 +11,7 @@ impl MainState {
         MainState {
             login_state: LoginState::new(),
             download_state: DownloadState::new(),
+            game_state: GameState::new(),
         }
     }
 }
\ No newline at end of file
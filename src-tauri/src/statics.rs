@@ -2,4 +2,16 @@ use lazy_static::lazy_static;
 
 lazy_static! {
     pub static ref LOGIN_MAX_NUM: usize = 5;
-}
\ No newline at end of file
+}
+
+pub const VERSION_ROOT: &str = "versions/";
+pub const LIBRARY_ROOT: &str = "libraries/";
+pub const ASSETS_ROOT: &str = "assets/";
+pub const NATIVES_CACHE_ROOT: &str = "natives/";
+pub const RUNTIME_ROOT: &str = "runtimes/";
+pub const MODS_ROOT: &str = "mods/";
+pub const INSTANCES_ROOT: &str = "instances/";
+pub const CONFIG_FILE: &str = ".RMCL.config.json";
+
+pub const DOWNLOAD_CHUNK_SIZE: u64 = 3000000;
+pub const DOWNLOAD_PARALLELS: usize = 64;